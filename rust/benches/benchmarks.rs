@@ -94,7 +94,8 @@ impl<'c> Bencher<'c> {
             .bench_function(&format!("{}[coord]", self.name), |b| {
                 b.iter(|| {
                     for pt in coords.iter() {
-                        black_box(t.transform_into(pt, &mut out));
+                        t.transform_into(pt, &mut out);
+                        black_box(&out);
                     }
                 })
             });
@@ -105,7 +106,8 @@ impl<'c> Bencher<'c> {
         self.criterion
             .bench_function(&format!("{}[bulk]", self.name), |b| {
                 b.iter(|| {
-                    black_box(t.bulk_transform_into(&coord_refs, &mut bulk_refs));
+                    t.bulk_transform_into(&coord_refs, &mut bulk_refs);
+                    black_box(&bulk_refs);
                 })
             });
 
@@ -117,7 +119,8 @@ impl<'c> Bencher<'c> {
         self.criterion
             .bench_function(&format!("{}[column]", self.name), |b| {
                 b.iter(|| {
-                    black_box(t.column_transform_into(&col_refs, &mut out_refs));
+                    t.column_transform_into(&col_refs, &mut out_refs);
+                    black_box(&out_refs);
                 })
             });
     }
@@ -0,0 +1,180 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    ShortVec, Transformation,
+    graph::unit_conversion_factor,
+    transforms::{MapAxis, Scale, SequenceBuilder},
+};
+
+/// The type of a coordinate system axis, as described in OME-Zarr.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AxisType {
+    Space,
+    Time,
+    Channel,
+    Other(String),
+}
+
+impl From<&str> for AxisType {
+    fn from(value: &str) -> Self {
+        match value {
+            "space" => Self::Space,
+            "time" => Self::Time,
+            "channel" => Self::Channel,
+            s => Self::Other(s.to_string()),
+        }
+    }
+}
+
+/// A named axis of a coordinate system, with an optional type and unit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Axis {
+    name: String,
+    axis_type: Option<AxisType>,
+    unit: Option<String>,
+}
+
+impl Axis {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            axis_type: None,
+            unit: None,
+        }
+    }
+
+    /// Convenience constructor for a spatial axis with the given unit.
+    pub fn space(name: impl Into<String>, unit: impl Into<String>) -> Self {
+        Self::new(name).with_type(AxisType::Space).with_unit(unit)
+    }
+
+    /// Convenience constructor for a time axis with the given unit.
+    pub fn time(name: impl Into<String>, unit: impl Into<String>) -> Self {
+        Self::new(name).with_type(AxisType::Time).with_unit(unit)
+    }
+
+    /// Convenience constructor for a channel axis.
+    pub fn channel(name: impl Into<String>) -> Self {
+        Self::new(name).with_type(AxisType::Channel)
+    }
+
+    pub fn with_type(mut self, axis_type: impl Into<AxisType>) -> Self {
+        self.axis_type = Some(axis_type.into());
+        self
+    }
+
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn axis_type(&self) -> Option<&AxisType> {
+        self.axis_type.as_ref()
+    }
+
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+}
+
+/// Fails if any axis names are repeated.
+pub(crate) fn validate_axes(axes: &[Axis]) -> Result<(), String> {
+    let mut names = HashSet::with_capacity(axes.len());
+    for a in axes {
+        if !names.insert(a.name()) {
+            return Err(format!("Axis name '{}' is repeated", a.name()));
+        }
+    }
+    Ok(())
+}
+
+/// If the target axes are a re-ordering of the source axes,
+/// possibly in different but compatible units,
+/// find the transformation which maps one to the other.
+///
+/// Axes are matched by name, and must have the same type.
+/// Axes with different units are scaled by [unit_conversion_factor].
+pub(crate) fn axis_mapping(from: &[Axis], to: &[Axis]) -> Option<Arc<dyn Transformation>> {
+    if from.len() != to.len() {
+        return None;
+    }
+    let mut map: ShortVec<usize> = ShortVec::with_capacity(to.len());
+    let mut factors: ShortVec<f64> = ShortVec::with_capacity(to.len());
+    for tgt in to {
+        let src_idx = from.iter().position(|src| src.name == tgt.name)?;
+        let src = &from[src_idx];
+        if src.axis_type != tgt.axis_type {
+            return None;
+        }
+        let factor = match (src.unit(), tgt.unit()) {
+            (None, None) => 1.0,
+            (Some(a), Some(b)) => unit_conversion_factor(a, b)?,
+            _ => return None,
+        };
+        map.push(src_idx);
+        factors.push(factor);
+    }
+
+    let mut builder = SequenceBuilder::with_capacity(2);
+    builder
+        .add_transform(MapAxis::try_new(&map).ok()?)
+        .ok()?
+        .add_transform(Scale::try_new(&factors).ok()?)
+        .ok()?;
+    builder.build_any().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zyx() -> Vec<Axis> {
+        vec![
+            Axis::space("z", "micrometer"),
+            Axis::space("y", "micrometer"),
+            Axis::space("x", "micrometer"),
+        ]
+    }
+
+    fn check_mapping(from: &[Axis], to: &[Axis], input: &[f64], expected: &[f64]) {
+        let t = axis_mapping(from, to).unwrap();
+        let mut out = vec![f64::NAN; expected.len()];
+        t.transform_into(input, &mut out);
+        assert_eq!(out.as_slice(), expected);
+    }
+
+    #[test]
+    fn test_permutation() {
+        let mut xyz = zyx();
+        xyz.reverse();
+        check_mapping(&zyx(), &xyz, &[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0]);
+        assert!(axis_mapping(&zyx(), &zyx()).unwrap().is_identity());
+    }
+
+    #[test]
+    fn test_units() {
+        let mut xyz = zyx();
+        xyz.reverse();
+        xyz[0] = Axis::space("x", "nanometer");
+        check_mapping(&zyx(), &xyz, &[1.0, 2.0, 3.0], &[3000.0, 2.0, 1.0]);
+
+        xyz[0] = Axis::space("x", "second");
+        assert!(axis_mapping(&zyx(), &xyz).is_none());
+        xyz[0] = Axis::time("x", "micrometer");
+        assert!(axis_mapping(&zyx(), &xyz).is_none());
+        xyz[0] = Axis::new("x");
+        assert!(axis_mapping(&zyx(), &xyz).is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate_axes(&zyx()).is_ok());
+        let mut axes = zyx();
+        axes.push(Axis::time("z", "second"));
+        assert!(validate_axes(&axes).is_err());
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use petgraph::{prelude::*, visit::IntoEdgeReferences};
+
+use crate::{
+    Transformation,
+    graph::{
+        EdgeOrigin, GraphPath, TransformGraph,
+        search::{Step, shortest_path},
+    },
+};
+
+/// Points at which to evaluate a transformation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Samples {
+    /// Explicit points.
+    Points(Vec<Vec<f64>>),
+    /// A regular grid of `steps` points per dimension spanning the box, inclusive.
+    /// A single step samples the centre.
+    BoundingBox {
+        min: Vec<f64>,
+        max: Vec<f64>,
+        steps: usize,
+    },
+}
+
+impl Samples {
+    fn ndim(&self) -> Option<usize> {
+        match self {
+            Samples::Points(pts) => pts.first().map(|p| p.len()),
+            Samples::BoundingBox { min, .. } => Some(min.len()),
+        }
+    }
+
+    /// Fails if the points are not all of the given dimensionality.
+    fn points(&self, ndim: usize) -> Result<Vec<Vec<f64>>, String> {
+        match self {
+            Samples::Points(pts) => {
+                if pts.iter().any(|p| p.len() != ndim) {
+                    return Err(format!("Sample points must be {ndim}D"));
+                }
+                Ok(pts.clone())
+            }
+            Samples::BoundingBox { min, max, steps } => {
+                if min.len() != ndim || max.len() != ndim {
+                    return Err(format!("Bounding box must be {ndim}D"));
+                }
+                let axes: Vec<Vec<f64>> = min
+                    .iter()
+                    .zip(max)
+                    .map(|(lo, hi)| match steps {
+                        0 => vec![],
+                        1 => vec![(lo + hi) / 2.0],
+                        n => (0..*n)
+                            .map(|i| lo + (hi - lo) * i as f64 / (n - 1) as f64)
+                            .collect(),
+                    })
+                    .collect();
+                let mut out = vec![vec![]];
+                for ax in axes {
+                    out = out
+                        .into_iter()
+                        .flat_map(|pt: Vec<f64>| {
+                            ax.iter().map(move |v| {
+                                let mut p = pt.clone();
+                                p.push(*v);
+                                p
+                            })
+                        })
+                        .collect();
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// A cycle in a [TransformGraph], with the largest distance
+/// by which any sample point was moved by going around it.
+#[derive(Debug, Clone)]
+pub struct LoopResidual<C> {
+    path: GraphPath<C>,
+    max_residual: f64,
+}
+
+impl<C> LoopResidual<C> {
+    /// The cycle, starting and ending at the coordinate system in which samples were taken.
+    pub fn path(&self) -> &GraphPath<C> {
+        &self.path
+    }
+
+    /// The largest Euclidean distance between a sample point and its image around the cycle.
+    /// Points which could not be transformed (i.e. became NaN) are ignored.
+    pub fn max_residual(&self) -> f64 {
+        self.max_residual
+    }
+}
+
+/// The result of checking cyclic registrations with [TransformGraph::loop_closure].
+#[derive(Debug, Clone)]
+pub struct LoopClosure<C> {
+    loops: Vec<LoopResidual<C>>,
+    unclosed: Vec<(C, C)>,
+}
+
+impl<C> LoopClosure<C> {
+    /// The cycles which were checked, in descending order of residual,
+    /// so that the most inconsistent registrations come first.
+    pub fn loops(&self) -> &[LoopResidual<C>] {
+        &self.loops
+    }
+
+    pub fn into_loops(self) -> Vec<LoopResidual<C>> {
+        self.loops
+    }
+
+    /// The (source, target) of each explicit edge which lies on a cycle of explicit edges,
+    /// but which could not be checked because no cycle through it can be composed,
+    /// e.g. because it would traverse edges in reverse which have no inverse.
+    pub fn unclosed(&self) -> &[(C, C)] {
+        &self.unclosed
+    }
+}
+
+impl<C: std::hash::Hash + Eq + Clone> TransformGraph<C> {
+    /// Check that cyclic registrations compose to (approximately) the identity.
+    ///
+    /// A spanning forest of the explicit edges is found,
+    /// using only steps which can be taken: edges forwards, or in reverse if they are reversible.
+    /// Each explicit edge `A -> B` not in the forest closes a cycle with a path from `B` back to `A`:
+    /// through the forest where possible, otherwise the cheapest path along explicit edges.
+    /// Where every edge is reversible, these fundamental cycles form a cycle basis:
+    /// if every registration around them is consistent, so is every cycle of explicit edges.
+    /// Edges for which no cycle can be composed are reported as [unclosed](LoopClosure::unclosed).
+    ///
+    /// Each cycle is composed and evaluated at the samples for the coordinate system
+    /// where it starts, as given by `samples`;
+    /// coordinate systems without samples are skipped.
+    ///
+    /// Fails if samples have the wrong dimensionality for their coordinate system.
+    pub fn loop_closure<F>(&self, samples: F) -> Result<LoopClosure<C>, String>
+    where
+        F: Fn(&C) -> Option<Samples>,
+    {
+        let forest = self.explicit_spanning_forest();
+        let tree_edges: HashSet<EdgeIndex> = forest
+            .values()
+            .filter_map(|(parent, _)| parent.map(|(_, s)| s.edge))
+            .collect();
+        let mut points: HashMap<NodeIndex, Vec<Vec<f64>>> = HashMap::default();
+        let mut loops: Vec<LoopResidual<C>> = vec![];
+        let mut unclosed = vec![];
+
+        for e in (&self.graph).edge_references() {
+            if e.weight().origin != EdgeOrigin::Explicit || tree_edges.contains(&e.id()) {
+                continue;
+            }
+            let (u, v) = (e.source(), e.target());
+            let ndim = self.node_info(u).expect("node exists").ndim;
+            let pts = match points.get(&u) {
+                Some(p) => p,
+                None => {
+                    let Some(s) = samples(&self.graph[u]) else {
+                        continue;
+                    };
+                    if s.ndim().is_some_and(|n| n != ndim) {
+                        return Err(format!("Sample points must be {ndim}D"));
+                    }
+                    points.entry(u).or_insert(s.points(ndim)?)
+                }
+            };
+
+            let through_forest = forest_path(&forest, v, u).and_then(|ret| {
+                let mut steps = vec![Step::forward(e.id())];
+                steps.extend(ret);
+                let t = self.compose(&steps, ndim)?;
+                Some((steps, t))
+            });
+            let Some((steps, transform)) = through_forest.or_else(|| self.explicit_cycle(e.id()))
+            else {
+                unclosed.push((self.graph[u].clone(), self.graph[v].clone()));
+                continue;
+            };
+            loops.push(LoopResidual {
+                max_residual: max_residual(transform.as_ref(), pts),
+                path: self.graph_path(u, &steps, transform),
+            });
+        }
+
+        loops.sort_by(|a, b| b.max_residual.total_cmp(&a.max_residual));
+        Ok(LoopClosure { loops, unclosed })
+    }
+
+    /// The cheapest cycle along explicit edges which starts with the given edge
+    /// and does not simply traverse it back in reverse, if one can be composed.
+    fn explicit_cycle(&self, edge: EdgeIndex) -> Option<(Vec<Step>, Arc<dyn Transformation>)> {
+        let (u, v) = self.graph.edge_endpoints(edge).expect("edge exists");
+        let ndim = self.node_info(u).expect("node exists").ndim;
+        let mut excluded: HashSet<Step> = self
+            .graph
+            .edge_indices()
+            .filter(|e| self.graph[*e].origin != EdgeOrigin::Explicit)
+            .flat_map(|e| [Step::forward(e), Step::reverse(e)])
+            .collect();
+        excluded.insert(Step::reverse(edge));
+        loop {
+            let (_, ret) = shortest_path(
+                &self.graph,
+                v,
+                u,
+                self.inverse_penalty,
+                false,
+                &HashSet::default(),
+                &excluded,
+            )?;
+            let mut steps = Vec::with_capacity(ret.len() + 1);
+            steps.push(Step::forward(edge));
+            steps.extend(ret);
+            // if an inverse did not exist, it is now known not to, so search again
+            if let Some(t) = self.compose(&steps, ndim) {
+                return Some((steps, t));
+            }
+        }
+    }
+
+    /// A spanning forest of the explicit edges,
+    /// traversed forwards, or in reverse if they are reversible.
+    fn explicit_spanning_forest(&self) -> Forest {
+        let mut forest = Forest::default();
+        let mut queue = VecDeque::new();
+        for root in self.graph.node_indices() {
+            if forest.contains_key(&root) {
+                continue;
+            }
+            forest.insert(root, (None, 0));
+            queue.push_back(root);
+            while let Some(node) = queue.pop_front() {
+                let depth = forest[&node].1 + 1;
+                let forward = self
+                    .graph
+                    .edges_directed(node, Outgoing)
+                    .map(|e| (Step::forward(e.id()), e.target(), e.weight()));
+                let reverse = self
+                    .graph
+                    .edges_directed(node, Incoming)
+                    .filter(|e| e.weight().can_reverse())
+                    .map(|e| (Step::reverse(e.id()), e.source(), e.weight()));
+                for (step, next, edge) in forward.chain(reverse) {
+                    if edge.origin != EdgeOrigin::Explicit || forest.contains_key(&next) {
+                        continue;
+                    }
+                    forest.insert(next, (Some((node, step)), depth));
+                    queue.push_back(next);
+                }
+            }
+        }
+        forest
+    }
+
+    /// As [Self::loop_closure], but only return cycles whose residual exceeds the tolerance.
+    pub fn inconsistent_loops<F>(
+        &self,
+        samples: F,
+        tolerance: f64,
+    ) -> Result<LoopClosure<C>, String>
+    where
+        F: Fn(&C) -> Option<Samples>,
+    {
+        let mut out = self.loop_closure(samples)?;
+        out.loops.retain(|r| r.max_residual > tolerance);
+        Ok(out)
+    }
+}
+
+/// For each node, its parent and the step from it (None for a root), and its depth.
+type Forest = HashMap<NodeIndex, (Option<(NodeIndex, Step)>, usize)>;
+
+/// The steps from one node to another in a spanning forest:
+/// up to their common ancestor, then down.
+/// None if they are in different trees.
+fn forest_path(forest: &Forest, from: NodeIndex, to: NodeIndex) -> Option<Vec<Step>> {
+    let (mut up_node, mut down_node) = (from, to);
+    let mut up = vec![];
+    let mut down = vec![];
+    while up_node != down_node {
+        let (up_parent, up_depth) = forest[&up_node];
+        let (down_parent, down_depth) = forest[&down_node];
+        if up_depth >= down_depth {
+            let (p, step) = up_parent?;
+            up.push(Step {
+                edge: step.edge,
+                reversed: !step.reversed,
+            });
+            up_node = p;
+        }
+        if down_depth >= up_depth {
+            let (p, step) = down_parent?;
+            down.push(step);
+            down_node = p;
+        }
+    }
+    up.extend(down.into_iter().rev());
+    Some(up)
+}
+
+fn max_residual(t: &dyn Transformation, pts: &[Vec<f64>]) -> f64 {
+    let mut buf = vec![f64::NAN; t.output_ndim()];
+    pts.iter()
+        .map(|p| {
+            t.transform_into(p, &mut buf);
+            p.iter()
+                .zip(buf.iter())
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        })
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Samples;
+    use crate::{TransformGraph, Transformation, transforms::Translate};
+
+    fn translate(t: &[f64]) -> Arc<dyn Transformation> {
+        Arc::new(Translate::try_new(t).unwrap())
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let s = Samples::BoundingBox {
+            min: vec![0.0, 0.0],
+            max: vec![1.0, 2.0],
+            steps: 2,
+        };
+        assert_eq!(
+            s.points(2).unwrap(),
+            vec![
+                vec![0.0, 0.0],
+                vec![0.0, 2.0],
+                vec![1.0, 0.0],
+                vec![1.0, 2.0]
+            ]
+        );
+        assert!(s.points(3).is_err());
+    }
+
+    #[test]
+    fn test_loop_closure() {
+        let mut tg = TransformGraph::default();
+        tg.add_edge("a", "b", translate(&[1.0, 0.0]), 1.0, true)
+            .unwrap();
+        tg.add_edge("b", "c", translate(&[0.0, 1.0]), 1.0, true)
+            .unwrap();
+        tg.add_edge("c", "a", translate(&[-1.0, -1.5]), 1.0, true)
+            .unwrap();
+        tg.add_edge("c", "d", translate(&[1.0, 1.0]), 1.0, true)
+            .unwrap();
+
+        let samples = |_: &&str| {
+            Some(Samples::BoundingBox {
+                min: vec![0.0, 0.0],
+                max: vec![10.0, 10.0],
+                steps: 3,
+            })
+        };
+        let closure = tg.loop_closure(samples).unwrap();
+        assert!(closure.unclosed().is_empty());
+        let loops = closure.loops();
+        // the a-b-c cycle; its reverse only traverses edges in reverse,
+        // and the c-d edge has no cycle
+        assert_eq!(loops.len(), 1);
+        for l in loops.iter() {
+            assert_eq!(l.path().edges().len(), 3);
+            assert_eq!(l.path().nodes().first(), l.path().nodes().last());
+            approx::assert_relative_eq!(l.max_residual(), 0.5);
+        }
+
+        assert!(
+            tg.inconsistent_loops(samples, 1.0)
+                .unwrap()
+                .loops()
+                .is_empty()
+        );
+        assert_eq!(
+            tg.inconsistent_loops(samples, 0.1).unwrap().loops().len(),
+            1
+        );
+
+        // a second, independent cycle c-d-e, and the a-b-c-d-e cycle which they combine into
+        tg.add_edge("d", "e", translate(&[0.0, 2.0]), 1.0, true)
+            .unwrap();
+        tg.add_edge("e", "c", translate(&[-1.0, -5.0]), 1.0, true)
+            .unwrap();
+        let closure = tg.loop_closure(samples).unwrap();
+        let loops = closure.loops();
+        assert_eq!(loops.len(), 2);
+        approx::assert_relative_eq!(loops[0].max_residual(), 2.0);
+        approx::assert_relative_eq!(loops[1].max_residual(), 0.5);
+        for l in loops.iter() {
+            assert_eq!(l.path().nodes().first(), l.path().nodes().last());
+        }
+
+        // no samples
+        assert!(tg.loop_closure(|_| None).unwrap().loops().is_empty());
+        // wrong dimensionality
+        assert!(
+            tg.loop_closure(|_| Some(Samples::Points(vec![vec![0.0]])))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_loop_closure_irreversible() {
+        let mut tg = TransformGraph::default();
+        tg.add_edge("a", "b", translate(&[1.0, 0.0]), 1.0, false)
+            .unwrap();
+        tg.add_edge("b", "c", translate(&[0.0, 1.0]), 1.0, false)
+            .unwrap();
+        tg.add_edge("c", "a", translate(&[-1.0, -1.5]), 1.0, false)
+            .unwrap();
+        tg.add_edge("a", "c", translate(&[1.0, 3.0]), 1.0, false)
+            .unwrap();
+
+        let samples = |_: &&str| Some(Samples::Points(vec![vec![0.0, 0.0]]));
+        let closure = tg.loop_closure(samples).unwrap();
+        assert!(closure.unclosed().is_empty());
+        let loops = closure.loops();
+        // c-a-c, and the directed a-b-c cycle (from b), whose forest path would reverse a -> c
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].path().nodes(), &["c", "a", "c"]);
+        approx::assert_relative_eq!(loops[0].max_residual(), 1.5);
+        assert_eq!(loops[1].path().nodes(), &["b", "c", "a", "b"]);
+        approx::assert_relative_eq!(loops[1].max_residual(), 0.5);
+
+        // on undirected cycles, but no directed ones
+        tg.add_edge("d", "a", translate(&[1.0, 0.0]), 1.0, false)
+            .unwrap();
+        tg.add_edge("d", "b", translate(&[2.0, 0.0]), 1.0, false)
+            .unwrap();
+        let closure = tg.loop_closure(samples).unwrap();
+        assert_eq!(closure.loops().len(), 2);
+        let mut unclosed = closure.unclosed().to_vec();
+        unclosed.sort();
+        assert_eq!(unclosed, vec![("d", "a"), ("d", "b")]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::{Display, Write};
+
+use petgraph::{prelude::*, visit::IntoEdgeReferences};
+
+use crate::graph::{EdgeOrigin, TransformGraph};
+
+/// Escape a string for use in a double-quoted DOT ID.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<C: std::hash::Hash + Eq + Clone + Display> TransformGraph<C> {
+    /// Export the graph as text in Graphviz's DOT language.
+    ///
+    /// Nodes are labelled with the coordinate system and its dimensionality;
+    /// edges with the kind of transformation (see [crate::Transformation::kind]) and cost.
+    /// Edges which may be traversed in reverse have a dashed reverse edge,
+    /// labelled with the cost including the inverse penalty (see [TransformGraph::set_inverse_penalty]).
+    /// Edges added between coordinate systems with compatible axes,
+    /// or between subspaces, are dotted.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph {\n");
+        for idx in self.graph.node_indices() {
+            let ndim = self.node_info(idx).expect("node exists").ndim;
+            let label = escape(&self.graph[idx].to_string());
+            writeln!(out, "    {} [label=\"{label}\\n{ndim}D\"];", idx.index())
+                .expect("writing to string cannot fail");
+        }
+        for e in (&self.graph).edge_references() {
+            let edge = e.weight();
+            let style = match edge.origin() {
+                EdgeOrigin::AxisMapping | EdgeOrigin::Subspace => ", style=dotted",
+                _ => "",
+            };
+            writeln!(
+                out,
+                "    {} -> {} [label=\"{}\\ncost={}\"{style}];",
+                e.source().index(),
+                e.target().index(),
+                escape(edge.transform().kind()),
+                edge.cost(),
+            )
+            .expect("writing to string cannot fail");
+            if edge.is_reversible() {
+                writeln!(
+                    out,
+                    "    {} -> {} [label=\"{}^-1\\ncost={}\", style=dashed];",
+                    e.target().index(),
+                    e.source().index(),
+                    escape(edge.transform().kind()),
+                    edge.cost() + self.inverse_penalty,
+                )
+                .expect("writing to string cannot fail");
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        TransformGraph, Transformation,
+        transforms::{Scale, Translate},
+    };
+
+    #[test]
+    fn test_kind() {
+        let t: Arc<dyn Transformation> = Arc::new(Translate::try_new(&[1.0]).unwrap());
+        assert_eq!(t.kind(), "Translate");
+        assert_eq!(Scale::try_new(&[1.0]).unwrap().kind(), "Scale");
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut tg: TransformGraph<String> = TransformGraph::default();
+        tg.add_edge(
+            "a \"quoted\"",
+            "b",
+            Arc::new(Translate::try_new(&[1.0, 2.0]).unwrap()),
+            1.5,
+            true,
+        )
+        .unwrap();
+        let dot = tg.to_dot();
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("0 [label=\"a \\\"quoted\\\"\\n2D\"];"));
+        assert!(dot.contains("1 [label=\"b\\n2D\"];"));
+        assert!(dot.contains("0 -> 1 [label=\"Translate\\ncost=1.5\"];"));
+        assert!(dot.contains("1 -> 0 [label=\"Translate^-1\\ncost=1.6\", style=dashed];"));
+    }
+}
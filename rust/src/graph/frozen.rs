@@ -0,0 +1,234 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use petgraph::prelude::*;
+
+use crate::{Transformation, graph::TransformGraph, transforms::Identity};
+
+type PathCell = OnceLock<Option<Arc<dyn Transformation>>>;
+
+#[derive(Debug)]
+struct Frozen<C: std::hash::Hash + Eq + Clone> {
+    graph: TransformGraph<C>,
+    /// Dense index of each coordinate system, by node index.
+    dense: HashMap<NodeIndex, usize>,
+    /// Path from dense index `i` to dense index `j` is at `paths[i][j]`;
+    /// each row is allocated when its source is first looked up.
+    paths: Box<[OnceLock<Box<[PathCell]>>]>,
+}
+
+/// An immutable snapshot of a [TransformGraph], for sharing between threads.
+///
+/// Unlike [TransformGraph::find_path], lookups do not take a lock,
+/// so they never wait for another thread.
+/// Paths are either all computed when the graph is frozen
+/// (see [TransformGraph::freeze_precomputed]),
+/// or computed on first use (see [TransformGraph::freeze]);
+/// after that, a lookup only reads the stored path.
+///
+/// Cloning is cheap, as the snapshot is reference-counted.
+#[derive(Debug, Clone)]
+pub struct FrozenTransformGraph<C: std::hash::Hash + Eq + Clone>(Arc<Frozen<C>>);
+
+impl<C: std::hash::Hash + Eq + Clone> FrozenTransformGraph<C> {
+    fn new(mut graph: TransformGraph<C>) -> Self {
+        graph.path_cache.clear_mut();
+        let dense: HashMap<_, _> = graph
+            .graph
+            .node_indices()
+            .enumerate()
+            .map(|(i, idx)| (idx, i))
+            .collect();
+        let paths = (0..dense.len()).map(|_| OnceLock::new()).collect();
+        Self(Arc::new(Frozen {
+            graph,
+            dense,
+            paths,
+        }))
+    }
+
+    /// Compute the paths between every pair of coordinate systems.
+    fn precompute(&self) {
+        for u in self.0.graph.graph.node_indices() {
+            for v in self.0.graph.graph.node_indices() {
+                self.path(u, v);
+            }
+        }
+    }
+
+    /// The paths from a source, allocating them if this is its first lookup.
+    fn row(&self, u: NodeIndex) -> &[PathCell] {
+        let inner = &self.0;
+        let cell = &inner.paths[inner.dense[&u]];
+        if let Some(row) = cell.get() {
+            return row;
+        }
+        // if another thread sets the row first, this one is dropped unused
+        let _ = cell.set((0..inner.dense.len()).map(|_| OnceLock::new()).collect());
+        cell.get().expect("row was set")
+    }
+
+    fn path(&self, u: NodeIndex, v: NodeIndex) -> Option<Arc<dyn Transformation>> {
+        let inner = &self.0;
+        let cell = &self.row(u)[inner.dense[&v]];
+        if let Some(path) = cell.get() {
+            return path.clone();
+        }
+        // threads racing on the same cell each compute the path rather than blocking;
+        // all of them return whichever is stored first
+        let ndim = inner.graph.node_info(u).expect("node exists").ndim;
+        let path = if u == v {
+            Some(Arc::new(Identity::new(ndim)) as Arc<dyn Transformation>)
+        } else {
+            inner.graph.find_route(u, v, ndim).map(|r| r.transform)
+        };
+        let _ = cell.set(path);
+        cell.get().expect("path was set").clone()
+    }
+
+    /// Get a transformation between two coordinate systems, if it exists.
+    ///
+    /// See [TransformGraph::find_path].
+    pub fn find_path<Q1, Q2>(&self, from: &Q1, to: &Q2) -> Option<Arc<dyn Transformation>>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let (u, v) = self.0.graph.node_pair(from, to).ok()?;
+        self.path(u, v)
+    }
+
+    /// Whether the coordinate system exists in the graph.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        C: Borrow<Q>,
+        Q: std::hash::Hash + Eq + ?Sized,
+    {
+        self.0.graph.coord_systems.contains_key(key)
+    }
+
+    /// Get the axes of a coordinate system, if they have been declared.
+    pub fn axes<Q>(&self, key: &Q) -> Option<&[crate::Axis]>
+    where
+        C: Borrow<Q>,
+        Q: std::hash::Hash + Eq + ?Sized,
+    {
+        self.0.graph.axes(key)
+    }
+}
+
+impl<C: std::hash::Hash + Eq + Clone> TransformGraph<C> {
+    /// Freeze the graph into an immutable snapshot,
+    /// in which each path is computed on first use.
+    ///
+    /// Freezing is cheap, and memory is only allocated for sources which are looked up.
+    /// A lookup never blocks, but the first lookups of a path may each compute it
+    /// if they happen concurrently; later lookups only read it.
+    pub fn freeze(self) -> FrozenTransformGraph<C> {
+        FrozenTransformGraph::new(self)
+    }
+
+    /// Freeze the graph into an immutable snapshot,
+    /// computing the path between every pair of coordinate systems up front.
+    ///
+    /// This is quadratic in the number of coordinate systems, in time and memory;
+    /// in return, every lookup only reads a stored path.
+    pub fn freeze_precomputed(self) -> FrozenTransformGraph<C> {
+        let frozen = FrozenTransformGraph::new(self);
+        frozen.precompute();
+        frozen
+    }
+}
+
+impl<C: std::hash::Hash + Eq + Clone> From<TransformGraph<C>> for FrozenTransformGraph<C> {
+    fn from(value: TransformGraph<C>) -> Self {
+        value.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{TransformGraph, Transformation, transforms::Translate};
+
+    fn make_graph() -> TransformGraph<&'static str> {
+        let mut tg = TransformGraph::default();
+        for (src, tgt, t, inv) in [
+            ("a", "b", [1.0, 2.0], true),
+            ("b", "c", [10.0, 20.0], true),
+            ("b", "d", [100.0, 200.0], false),
+        ] {
+            tg.add_edge(
+                src,
+                tgt,
+                Arc::new(Translate::try_new(&t).unwrap()),
+                1.0,
+                inv,
+            )
+            .unwrap();
+        }
+        tg
+    }
+
+    fn transform(t: Arc<dyn Transformation>) -> Vec<f64> {
+        let mut out = vec![f64::NAN; 2];
+        t.transform_into(&[0.0, 0.0], &mut out);
+        out
+    }
+
+    #[test]
+    fn test_frozen_matches() {
+        let tg = make_graph();
+        let keys = ["a", "b", "c", "d", "e"];
+        let expected: Vec<_> = keys
+            .iter()
+            .flat_map(|src| keys.iter().map(|tgt| tg.find_path(src, tgt).map(transform)))
+            .collect();
+
+        for frozen in [make_graph().freeze(), make_graph().freeze_precomputed()] {
+            let actual: Vec<_> = keys
+                .iter()
+                .flat_map(|src| {
+                    keys.iter()
+                        .map(|tgt| frozen.find_path(src, tgt).map(transform))
+                })
+                .collect();
+            assert_eq!(actual, expected);
+            assert!(frozen.contains("a"));
+            assert!(!frozen.contains("e"));
+        }
+    }
+
+    #[test]
+    fn test_frozen_lazy_rows() {
+        let allocated = |f: &super::FrozenTransformGraph<&str>| {
+            f.0.paths.iter().filter(|r| r.get().is_some()).count()
+        };
+        let frozen = make_graph().freeze();
+        assert_eq!(allocated(&frozen), 0);
+        frozen.find_path("a", "c").unwrap();
+        frozen.find_path("a", "d").unwrap();
+        assert_eq!(allocated(&frozen), 1);
+        assert_eq!(allocated(&make_graph().freeze_precomputed()), 4);
+    }
+
+    #[test]
+    fn test_frozen_threads() {
+        let frozen = make_graph().freeze();
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                let f = frozen.clone();
+                s.spawn(move || {
+                    assert_eq!(transform(f.find_path("a", "c").unwrap()), vec![11.0, 22.0]);
+                    assert!(f.find_path("d", "a").is_none());
+                });
+            }
+        });
+    }
+}
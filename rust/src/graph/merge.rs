@@ -0,0 +1,234 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use petgraph::{prelude::*, visit::IntoEdgeReferences};
+
+use crate::graph::TransformGraph;
+
+impl<C: std::hash::Hash + Eq + Clone> TransformGraph<C> {
+    /// Add all of the coordinate systems and edges of another graph to this one,
+    /// renaming each coordinate system with `key_map`.
+    ///
+    /// Coordinate systems which already exist in this graph are shared,
+    /// so must have the same dimensionality, and the same axes if both declare them.
+    /// Edges keep their cost and whether they may be traversed in reverse.
+    /// No edges are added automatically between the two graphs,
+    /// even where coordinate systems' axes are a re-ordering of each other
+    /// (see [Self::add_coordinate_system]):
+    /// two images may share axis names without being registered to each other.
+    /// Edges may then be added explicitly between coordinate systems from either graph,
+    /// e.g. with [Self::add_edge] or [Self::add_axis_mapping].
+    ///
+    /// Fails without modifying this graph if `key_map` maps two coordinate systems to the same key,
+    /// or if any coordinate system conflicts with an existing one.
+    ///
+    /// Merging clears all cached paths.
+    pub fn merge<D, F>(&mut self, other: TransformGraph<D>, mut key_map: F) -> Result<(), String>
+    where
+        D: std::hash::Hash + Eq + Clone,
+        F: FnMut(&D) -> C,
+    {
+        let mut keys: HashMap<NodeIndex, C> = HashMap::with_capacity(other.coord_systems.len());
+        let mut seen = HashSet::with_capacity(other.coord_systems.len());
+        for idx in other.graph.node_indices() {
+            let key = key_map(&other.graph[idx]);
+            if !seen.insert(key.clone()) {
+                return Err("Key mapping gives the same key to several coordinate systems".into());
+            }
+            let theirs = other.node_info(idx).expect("node exists");
+            if let Some(ours) = self.coord_systems.get(&key) {
+                if ours.ndim != theirs.ndim {
+                    return Err(format!(
+                        "Existing coordinate system is {}D; new is {}D",
+                        ours.ndim, theirs.ndim
+                    ));
+                }
+                if let (Some(a), Some(b)) = (&ours.axes, &theirs.axes)
+                    && a != b
+                {
+                    return Err("Coordinate system already has different axes".into());
+                }
+            }
+            keys.insert(idx, key);
+        }
+
+        self.path_cache.clear_mut();
+        let mut nodes = HashMap::with_capacity(keys.len());
+        for idx in other.graph.node_indices() {
+            let theirs = other.node_info(idx).expect("node exists");
+            let key = keys[&idx].clone();
+            let u = self
+                .ensure_coord_system(key.clone(), theirs.ndim)
+                .expect("already checked dimensionality");
+            let ours = self.coord_systems.get_mut(&key).expect("node exists");
+            if ours.axes.is_none() {
+                ours.axes = theirs.axes.clone();
+            }
+            nodes.insert(idx, u);
+        }
+
+        for e in (&other.graph).edge_references() {
+            let edge = e.weight();
+            self.add_or_merge_edge(
+                nodes[&e.source()],
+                nodes[&e.target()],
+                edge.transform.clone(),
+                edge.cost.0,
+                edge.origin,
+                edge.reversible,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The key of a coordinate system in a namespace, as used by [TransformGraph::merge_namespaced].
+pub fn namespaced(namespace: &str, key: impl Display) -> String {
+    format!("{namespace}/{key}")
+}
+
+impl TransformGraph<String> {
+    /// As [Self::merge], renaming each of the other graph's coordinate systems
+    /// to `"{namespace}/{key}"` (see [namespaced]).
+    ///
+    /// This allows several graphs which use the same coordinate system names,
+    /// such as those of several OME-Zarr images, to be combined.
+    pub fn merge_namespaced<D>(
+        &mut self,
+        other: TransformGraph<D>,
+        namespace: &str,
+    ) -> Result<(), String>
+    where
+        D: std::hash::Hash + Eq + Clone + Display,
+    {
+        self.merge(other, |k| namespaced(namespace, k))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::namespaced;
+    use crate::{Axis, TransformGraph, transforms::Scale};
+
+    fn image_graph(scale: f64) -> TransformGraph<&'static str> {
+        let mut tg = TransformGraph::default();
+        tg.add_coordinate_system(
+            "physical",
+            vec![
+                Axis::space("y", "micrometer"),
+                Axis::space("x", "micrometer"),
+            ],
+        )
+        .unwrap();
+        tg.add_edge(
+            "array",
+            "physical",
+            Arc::new(Scale::try_new(&[scale, scale]).unwrap()),
+            1.0,
+            true,
+        )
+        .unwrap();
+        tg
+    }
+
+    fn check_path(tg: &TransformGraph<String>, from: &str, to: &str, expected: &[f64]) {
+        let t = tg.find_path(from, to).unwrap();
+        let mut out = vec![f64::NAN; expected.len()];
+        t.transform_into(&[1.0, 1.0], &mut out);
+        assert_eq!(out.as_slice(), expected);
+    }
+
+    #[test]
+    fn test_merge_namespaced() {
+        let mut scene: TransformGraph<String> = TransformGraph::default();
+        scene
+            .add_coordinate_system(
+                "world",
+                vec![
+                    Axis::space("x", "micrometer"),
+                    Axis::space("y", "micrometer"),
+                ],
+            )
+            .unwrap();
+        scene.merge_namespaced(image_graph(2.0), "img1").unwrap();
+        scene.merge_namespaced(image_graph(4.0), "img2").unwrap();
+
+        check_path(&scene, "img1/array", "img1/physical", &[2.0, 2.0]);
+        check_path(&scene, "img2/physical", "img2/array", &[0.25, 0.25]);
+        // systems with compatible axes are not linked across graphs
+        assert!(scene.find_path("img1/array", "world").is_none());
+        assert!(scene.find_path("img1/array", "img2/array").is_none());
+
+        scene.add_axis_mapping("img1/physical", "world").unwrap();
+        check_path(&scene, "img1/array", "world", &[2.0, 2.0]);
+        assert!(scene.find_path("img1/array", "img2/array").is_none());
+
+        // cross-graph edges
+        scene
+            .add_edge(
+                namespaced("img1", "physical"),
+                namespaced("img2", "physical"),
+                Arc::new(Scale::try_new(&[2.0, 2.0]).unwrap()),
+                0.5,
+                true,
+            )
+            .unwrap();
+        check_path(&scene, "img1/array", "img2/array", &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_merge_keeps_axis_mappings() {
+        let mut other = image_graph(2.0);
+        other
+            .add_coordinate_system(
+                "physical_xy",
+                vec![
+                    Axis::space("x", "micrometer"),
+                    Axis::space("y", "micrometer"),
+                ],
+            )
+            .unwrap();
+        let mut scene: TransformGraph<String> = TransformGraph::default();
+        scene.merge_namespaced(other, "img").unwrap();
+        check_path(&scene, "img/array", "img/physical_xy", &[2.0, 2.0]);
+        assert_eq!(scene.axes("img/physical_xy").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let mut scene: TransformGraph<String> = TransformGraph::default();
+        scene
+            .add_edge(
+                "img/array",
+                "other",
+                Arc::new(Scale::try_new(&[1.0, 1.0, 1.0]).unwrap()),
+                1.0,
+                false,
+            )
+            .unwrap();
+        assert!(scene.merge_namespaced(image_graph(2.0), "img").is_err());
+        assert!(scene.axes("img/physical").is_none());
+        assert!(
+            scene
+                .merge(image_graph(2.0), |_| "same".to_string())
+                .is_err()
+        );
+
+        // shared coordinate systems are merged
+        scene
+            .merge(image_graph(2.0), |k| {
+                if *k == "physical" { "shared" } else { "mine" }.to_string()
+            })
+            .unwrap();
+        scene
+            .merge(image_graph(4.0), |k| {
+                if *k == "physical" { "shared" } else { "yours" }.to_string()
+            })
+            .unwrap();
+        check_path(&scene, "mine", "yours", &[0.5, 0.5]);
+    }
+}
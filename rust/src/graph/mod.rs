@@ -0,0 +1,1424 @@
+use ordered_float::OrderedFloat;
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use petgraph::prelude::*;
+
+use crate::{
+    Transformation,
+    transforms::{Identity, SequenceBuilder, approx_eq},
+};
+
+mod axis;
+pub use axis::{Axis, AxisType};
+use axis::{axis_mapping, validate_axes};
+mod closure;
+pub use closure::{LoopClosure, LoopResidual, Samples};
+mod dot;
+mod frozen;
+pub use frozen::FrozenTransformGraph;
+mod merge;
+pub use merge::namespaced;
+mod path;
+pub use path::GraphPath;
+mod reachable;
+mod search;
+mod subspace;
+use search::Step;
+mod units;
+pub use units::unit_conversion_factor;
+
+const DEFAULT_COST: f64 = 1.0;
+
+/// Cost of edges inserted to re-order axes and/or convert units between coordinate systems.
+const AXIS_MAPPING_COST: f64 = DEFAULT_COST;
+
+/// Additional cost of traversing a reversible edge in reverse,
+/// so that explicit edges are preferred over inverses of equal cost.
+const DEFAULT_INVERSE_PENALTY: f64 = 0.1;
+
+/// Parameter tolerance within which two edges between the same coordinate systems
+/// are considered duplicates.
+const DEDUP_TOLERANCE: f64 = 1e-12;
+
+/// How an edge came to be in the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeOrigin {
+    /// Added by the user.
+    Explicit,
+    /// An explicit edge traversed in reverse, using its inverse.
+    /// Only found in a [GraphPath]; such edges are not stored in the graph.
+    Inverse,
+    /// Added automatically between coordinate systems whose axes are a re-ordering of each other.
+    AxisMapping,
+    /// Added automatically to project a coordinate system onto a subset of its axes,
+    /// or to embed such a subspace in it.
+    Subspace,
+}
+
+#[derive(Debug, Clone)]
+pub struct Edge {
+    transform: Arc<dyn Transformation>,
+    cost: OrderedFloat<f64>,
+    origin: EdgeOrigin,
+    /// Whether the edge may be traversed in reverse.
+    reversible: bool,
+    /// The inverse transformation, computed when a path first traverses the edge in reverse.
+    /// None inside the lock means the transformation could not be inverted.
+    inverse: OnceLock<Option<Arc<dyn Transformation>>>,
+}
+
+impl Edge {
+    pub fn new_cost<T: Into<Arc<dyn Transformation>>>(transform: T, cost: f64) -> Self {
+        Self {
+            transform: transform.into(),
+            cost: OrderedFloat(cost),
+            origin: EdgeOrigin::Explicit,
+            reversible: false,
+            inverse: OnceLock::new(),
+        }
+    }
+
+    pub fn new<T: Into<Arc<dyn Transformation>>>(transform: T) -> Self {
+        Self::new_cost(transform, DEFAULT_COST)
+    }
+
+    fn with_origin(mut self, origin: EdgeOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    fn with_reversible(mut self, reversible: bool) -> Self {
+        self.reversible = reversible;
+        self
+    }
+
+    pub fn transform(&self) -> &Arc<dyn Transformation> {
+        &self.transform
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost.0
+    }
+
+    pub fn origin(&self) -> EdgeOrigin {
+        self.origin
+    }
+
+    /// Whether this edge is an explicit edge traversed in reverse.
+    pub fn is_inverse(&self) -> bool {
+        self.origin == EdgeOrigin::Inverse
+    }
+
+    /// Whether this edge may be traversed in reverse,
+    /// i.e. it was added with an inverse requested.
+    /// The inverse may still turn out not to exist when it is computed.
+    pub fn is_reversible(&self) -> bool {
+        self.reversible
+    }
+
+    /// Whether the edge is reversible and its inverse is not already known not to exist.
+    fn can_reverse(&self) -> bool {
+        self.reversible && self.inverse.get().is_none_or(|inv| inv.is_some())
+    }
+
+    /// Compute the inverse, if the edge is reversible and the inverse exists.
+    fn resolve_inverse(&self) -> Option<Arc<dyn Transformation>> {
+        if !self.reversible {
+            return None;
+        }
+        self.inverse.get_or_init(|| self.transform.invert()).clone()
+    }
+
+    /// This edge as traversed in reverse, if possible.
+    fn reversed(&self, inverse_penalty: f64) -> Option<Edge> {
+        let inverse = self.resolve_inverse()?;
+        let mut edge = Edge::new_cost(inverse, self.cost.0 + inverse_penalty)
+            .with_origin(EdgeOrigin::Inverse)
+            .with_reversible(true);
+        edge.inverse = OnceLock::from(Some(self.transform.clone()));
+        Some(edge)
+    }
+
+    /// Replace the transformation and cost, returning whether the cost decreased.
+    fn update(&mut self, transform: Arc<dyn Transformation>, cost: f64, reversible: bool) -> bool {
+        let cheaper = cost < self.cost.0;
+        self.transform = transform;
+        self.cost = OrderedFloat(cost);
+        self.reversible = reversible;
+        self.inverse = OnceLock::new();
+        cheaper
+    }
+}
+
+/// A path found between two coordinate systems.
+#[derive(Debug)]
+struct Route {
+    transform: Arc<dyn Transformation>,
+    /// The steps taken, in order.
+    steps: Vec<Step>,
+}
+
+impl Route {
+    fn uses_any(&self, edges: &[EdgeIndex]) -> bool {
+        self.steps.iter().any(|s| edges.contains(&s.edge))
+    }
+}
+
+type InnerPathCache = RwLock<HashMap<(NodeIndex, NodeIndex), Option<Arc<Route>>>>;
+
+/// Contains a locked map from (src, tgt) tuple to the possible route going from src to tgt,
+/// if it has been queried before.
+/// An entry will be unoccupied if this path has not been queried before,
+/// None if it was queried before and found not to exist,
+/// and Some if a route was found.
+#[derive(Debug, Default)]
+struct PathCache(InnerPathCache);
+
+impl PathCache {
+    /// Look-before-you-leap poison-clearing.
+    fn clear_poison(&self) {
+        if self.0.is_poisoned() {
+            self.0.clear_poison();
+        }
+    }
+
+    fn clear_mut(&mut self) {
+        self.clear_poison();
+        self.0.get_mut().unwrap().clear();
+    }
+
+    /// Keep only the entries for which the predicate returns true.
+    fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&(NodeIndex, NodeIndex), Option<&Route>) -> bool,
+    {
+        self.clear_poison();
+        self.0.get_mut().unwrap().retain(|k, v| f(k, v.as_deref()));
+    }
+
+    fn insert(
+        &self,
+        src: NodeIndex,
+        tgt: NodeIndex,
+        maybe_route: Option<Arc<Route>>,
+    ) -> Option<Option<Arc<Route>>> {
+        self.clear_poison();
+        self.0.write().unwrap().insert((src, tgt), maybe_route)
+    }
+
+    fn get(&self, src: &NodeIndex, tgt: &NodeIndex) -> Option<Option<Arc<Route>>> {
+        self.clear_poison();
+        let outer = self.0.read().unwrap();
+        outer.get(&(*src, *tgt)).cloned()
+    }
+}
+
+/// Identity transforms are replaced with an [Identity] of zero weight.
+fn simplify_identity(
+    transform: Arc<dyn Transformation>,
+    weight: f64,
+) -> (Arc<dyn Transformation>, f64) {
+    if transform.is_identity() {
+        (Arc::new(Identity::new(transform.input_ndim())), 0.0)
+    } else {
+        (transform, weight)
+    }
+}
+
+/// This type optimises for performance rather than a faithful representation of the given transformations.
+/// In practice, this means it filters out superfluous identity transformations.
+#[derive(Debug)]
+pub struct TransformGraph<C: std::hash::Hash + Eq + Clone> {
+    graph: StableDiGraph<C, Edge>,
+    coord_systems: HashMap<C, NodeInfo>,
+    path_cache: PathCache,
+    inverse_penalty: f64,
+}
+
+impl<C: std::hash::Hash + Eq + Clone> Default for TransformGraph<C> {
+    fn default() -> Self {
+        Self {
+            graph: Default::default(),
+            coord_systems: Default::default(),
+            path_cache: Default::default(),
+            inverse_penalty: DEFAULT_INVERSE_PENALTY,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NodeInfo {
+    idx: NodeIndex,
+    ndim: usize,
+    axes: Option<Vec<Axis>>,
+}
+
+impl<C: std::hash::Hash + Eq + Clone> TransformGraph<C> {
+    /// The additional cost of traversing an edge in reverse.
+    pub fn inverse_penalty(&self) -> f64 {
+        self.inverse_penalty
+    }
+
+    /// Set the additional cost of traversing an edge in reverse (default 0.1).
+    ///
+    /// This clears all cached paths.
+    pub fn set_inverse_penalty(&mut self, penalty: f64) {
+        self.inverse_penalty = penalty;
+        self.path_cache.clear_mut();
+    }
+
+    fn ensure_coord_system(&mut self, node: C, ndim: usize) -> Result<NodeIndex, String> {
+        if let Some(n) = self.coord_systems.get(&node) {
+            if n.ndim != ndim {
+                return Err(if n.axes.is_some() {
+                    format!(
+                        "Existing coordinate system has {} axes; new is {}D",
+                        n.ndim, ndim
+                    )
+                } else {
+                    format!(
+                        "Existing coordinate system is {}D; new is {}D",
+                        n.ndim, ndim
+                    )
+                });
+            }
+            Ok(n.idx)
+        } else {
+            let idx = self.graph.add_node(node.clone());
+            self.coord_systems.insert(
+                node,
+                NodeInfo {
+                    idx,
+                    ndim,
+                    axes: None,
+                },
+            );
+            Ok(idx)
+        }
+    }
+
+    /// Declare the axes of a coordinate system, adding it if it does not already exist.
+    ///
+    /// Fails if axis names are repeated,
+    /// if the existing coordinate system has a different dimensionality,
+    /// or if it already has different axes.
+    ///
+    /// If another coordinate system has the same axes, possibly in a different order
+    /// and/or in different but convertible units (see [unit_conversion_factor]),
+    /// edges are added in both directions between them,
+    /// containing a [crate::transforms::MapAxis] and/or a unit-conversion [crate::transforms::Scale].
+    /// Coordinate systems with identical axes are not connected,
+    /// as they may not describe the same space.
+    /// Paths never traverse two such edges in a row,
+    /// so they are not connected through a third system either.
+    ///
+    /// Declaring axes clears all cached paths.
+    pub fn add_coordinate_system(
+        &mut self,
+        key: impl Into<C>,
+        axes: Vec<Axis>,
+    ) -> Result<(), String> {
+        validate_axes(&axes)?;
+        let key = key.into();
+        let u = self.ensure_coord_system(key.clone(), axes.len())?;
+        let info = self.coord_systems.get_mut(&key).unwrap();
+        if let Some(existing) = info.axes.as_ref() {
+            if existing != &axes {
+                return Err("Coordinate system already has different axes".into());
+            }
+            return Ok(());
+        }
+        info.axes = Some(axes);
+        self.path_cache.clear_mut();
+        self.add_axis_mapping_edges(u);
+        Ok(())
+    }
+
+    /// Add an explicit edge from `src` to `tgt` which re-orders their axes
+    /// and/or converts their units (see [unit_conversion_factor]),
+    /// as found from their declared axes.
+    /// The edge may be traversed in reverse.
+    ///
+    /// Such edges are added automatically (see [Self::add_coordinate_system]),
+    /// except between coordinate systems with identical axes, or from different merged graphs;
+    /// an explicit edge can also be chained with others, replaced and removed.
+    ///
+    /// Fails if either coordinate system does not have declared axes,
+    /// or if the axes do not match by name and type, or have incompatible units.
+    ///
+    /// Adding edges clears all cached paths.
+    pub fn add_axis_mapping(&mut self, src: impl Into<C>, tgt: impl Into<C>) -> Result<(), String> {
+        let src = src.into();
+        let tgt = tgt.into();
+        let src_axes = self
+            .axes(&src)
+            .ok_or("Source coordinate system has no axes")?;
+        let tgt_axes = self
+            .axes(&tgt)
+            .ok_or("Target coordinate system has no axes")?;
+        let t = axis_mapping(src_axes, tgt_axes)
+            .ok_or("Axes do not match, or have incompatible units")?;
+        self.add_edge(src, tgt, t, AXIS_MAPPING_COST, true)?;
+        Ok(())
+    }
+
+    /// Add edges between the given coordinate system and any other
+    /// whose axes are a re-ordering of this one's, possibly in different units.
+    fn add_axis_mapping_edges(&mut self, u: NodeIndex) {
+        let Some(axes) = self.node_info(u).and_then(|n| n.axes.as_ref()) else {
+            return;
+        };
+        let mut to_add = vec![];
+        for other in self.coord_systems.values() {
+            if other.idx == u {
+                continue;
+            }
+            let Some(other_axes) = other.axes.as_ref() else {
+                continue;
+            };
+            let Some(fwd) = axis_mapping(axes, other_axes) else {
+                continue;
+            };
+            let Some(rev) = axis_mapping(other_axes, axes) else {
+                continue;
+            };
+            if fwd.is_identity() {
+                // axes are identical: these are different coordinate systems
+                continue;
+            }
+            to_add.push((u, other.idx, fwd));
+            to_add.push((other.idx, u, rev));
+        }
+        for (src, tgt, t) in to_add {
+            self.add_or_merge_edge(
+                src,
+                tgt,
+                t,
+                AXIS_MAPPING_COST,
+                EdgeOrigin::AxisMapping,
+                false,
+            );
+        }
+    }
+
+    fn node_info(&self, idx: NodeIndex) -> Option<&NodeInfo> {
+        self.coord_systems.get(self.graph.node_weight(idx)?)
+    }
+
+    /// Get the axes of a coordinate system, if they have been declared.
+    pub fn axes<Q>(&self, key: &Q) -> Option<&[Axis]>
+    where
+        C: Borrow<Q>,
+        Q: std::hash::Hash + Eq + ?Sized,
+    {
+        self.coord_systems.get(key)?.axes.as_deref()
+    }
+
+    /// If `with_inverse` is true, the edge may also be traversed in reverse
+    /// (with an additional cost, see [Self::set_inverse_penalty]).
+    /// The inverse is only computed when a path first uses it;
+    /// if it does not exist, paths are found which avoid it.
+    ///
+    /// Returns whether the edge may be traversed in reverse, i.e. `with_inverse`;
+    /// as the inverse is computed lazily, this does not guarantee that it exists.
+    /// Fails if the new edge's dimensionality is inconsistent with existing edges,
+    /// or with the number of axes declared for either coordinate system.
+    ///
+    /// Weight is set to 0 if the transformation is an identity.
+    ///
+    /// If an equivalent edge already exists between these coordinate systems
+    /// (see [approx_eq]), a duplicate is not added;
+    /// the lower of the two weights is kept,
+    /// and the edge is reversible if either was.
+    ///
+    /// Adding edges clears all cached paths.
+    pub fn add_edge(
+        &mut self,
+        src: impl Into<C>,
+        tgt: impl Into<C>,
+        transform: Arc<dyn Transformation>,
+        weight: f64,
+        with_inverse: bool,
+    ) -> Result<bool, String> {
+        self.path_cache.clear_mut();
+
+        let src_s = src.into();
+        let tgt_s = tgt.into();
+
+        // Do not add self-edges.
+        if src_s == tgt_s {
+            self.ensure_coord_system(src_s, transform.input_ndim())?;
+            return Ok(true);
+        }
+
+        let u = self.ensure_coord_system(src_s, transform.input_ndim())?;
+        let v = self.ensure_coord_system(tgt_s, transform.output_ndim())?;
+
+        let (t, w) = simplify_identity(transform, weight);
+        self.add_or_merge_edge(u, v, t, w, EdgeOrigin::Explicit, with_inverse);
+        Ok(with_inverse)
+    }
+
+    /// As [Self::add_edge], with the weight given by the transformation's
+    /// [Transformation::estimated_cost], plus a penalty.
+    ///
+    /// The penalty can be used to discourage routes through less accurate transformations,
+    /// e.g. an approximate registration, regardless of how cheap they are to compute.
+    pub fn add_edge_estimated(
+        &mut self,
+        src: impl Into<C>,
+        tgt: impl Into<C>,
+        transform: Arc<dyn Transformation>,
+        accuracy_penalty: f64,
+        with_inverse: bool,
+    ) -> Result<bool, String> {
+        let weight = transform.estimated_cost() + accuracy_penalty;
+        self.add_edge(src, tgt, transform, weight, with_inverse)
+    }
+
+    /// Add an edge, unless an equivalent edge already exists,
+    /// in which case keep the lower cost.
+    /// An existing edge which is added explicitly becomes explicit.
+    fn add_or_merge_edge(
+        &mut self,
+        u: NodeIndex,
+        v: NodeIndex,
+        transform: Arc<dyn Transformation>,
+        weight: f64,
+        origin: EdgeOrigin,
+        reversible: bool,
+    ) {
+        let existing = self
+            .graph
+            .edges_connecting(u, v)
+            .find(|e| approx_eq(&e.weight().transform, &transform, DEDUP_TOLERANCE))
+            .map(|e| e.id());
+        if let Some(idx) = existing {
+            let edge = &mut self.graph[idx];
+            edge.cost = edge.cost.min(OrderedFloat(weight));
+            edge.reversible |= reversible;
+            if origin == EdgeOrigin::Explicit {
+                // so that it can be removed or replaced like any other explicit edge
+                edge.origin = EdgeOrigin::Explicit;
+            }
+        } else {
+            self.graph.add_edge(
+                u,
+                v,
+                Edge::new_cost(transform, weight)
+                    .with_origin(origin)
+                    .with_reversible(reversible),
+            );
+        }
+    }
+
+    /// Remove the explicit edges from `src` to `tgt`,
+    /// which also prevents traversing them in reverse.
+    /// Edges added automatically between coordinate systems with declared axes are not removed.
+    ///
+    /// Returns the number of explicit edges removed.
+    ///
+    /// Only cached paths which used a removed edge are invalidated.
+    pub fn remove_edge<Q1, Q2>(&mut self, src: &Q1, tgt: &Q2) -> usize
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let Ok((u, v)) = self.node_pair(src, tgt) else {
+            return 0;
+        };
+        let removed = self.edges_with_origin(u, v, EdgeOrigin::Explicit);
+        for idx in removed.iter() {
+            self.graph.remove_edge(*idx);
+        }
+        self.invalidate_paths(&removed, false);
+        removed.len()
+    }
+
+    /// Remove a coordinate system and all of its edges.
+    ///
+    /// Returns whether the coordinate system existed.
+    ///
+    /// Only cached paths which started or ended at this coordinate system,
+    /// or passed through it, are invalidated.
+    pub fn remove_coordinate_system<Q>(&mut self, key: &Q) -> bool
+    where
+        C: Borrow<Q>,
+        Q: std::hash::Hash + Eq + ?Sized,
+    {
+        let Some(info) = self.coord_systems.remove(key) else {
+            return false;
+        };
+        let idx = info.idx;
+        let removed: Vec<_> = self
+            .graph
+            .edges_directed(idx, Outgoing)
+            .chain(self.graph.edges_directed(idx, Incoming))
+            .map(|e| e.id())
+            .collect();
+        self.graph.remove_node(idx);
+        // node and edge indices may be re-used, so stale entries must not be kept
+        self.path_cache.retain_mut(|(src, tgt), route| {
+            *src != idx && *tgt != idx && !route.is_some_and(|r| r.uses_any(&removed))
+        });
+        true
+    }
+
+    /// Replace the transformation and weight of the explicit edge from `src` to `tgt` in place.
+    ///
+    /// Fails if either coordinate system does not exist,
+    /// if there is no explicit edge between them,
+    /// or if the new transformation's dimensionality does not match the coordinate systems.
+    /// If there are several explicit edges between them, they are all replaced by the new one.
+    ///
+    /// As in [Self::add_edge], `with_inverse` determines whether the edge may be traversed in reverse,
+    /// which is returned.
+    ///
+    /// Cached paths which used the replaced edges are invalidated.
+    /// If the new weight is lower than the old, or the edge has newly become reversible,
+    /// other cached paths are also invalidated, as they may now be improved upon.
+    pub fn replace_edge<Q1, Q2>(
+        &mut self,
+        src: &Q1,
+        tgt: &Q2,
+        transform: Arc<dyn Transformation>,
+        weight: f64,
+        with_inverse: bool,
+    ) -> Result<bool, String>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let (u, v) = self.node_pair(src, tgt)?;
+        let in_ndim = self.node_info(u).expect("node exists").ndim;
+        let out_ndim = self.node_info(v).expect("node exists").ndim;
+        if transform.input_ndim() != in_ndim || transform.output_ndim() != out_ndim {
+            return Err(format!(
+                "Transformation is {}D -> {}D; coordinate systems are {}D -> {}D",
+                transform.input_ndim(),
+                transform.output_ndim(),
+                in_ndim,
+                out_ndim
+            ));
+        }
+        let mut explicit = self.edges_with_origin(u, v, EdgeOrigin::Explicit);
+        if explicit.is_empty() {
+            return Err("No explicit edge between these coordinate systems".into());
+        }
+
+        let (t, w) = simplify_identity(transform, weight);
+
+        let newly_reversible =
+            with_inverse && explicit.iter().all(|idx| !self.graph[*idx].reversible);
+        let fwd = explicit.remove(0);
+        let cheaper = self.graph[fwd].update(t, w, with_inverse);
+
+        let mut stale = Vec::with_capacity(explicit.len() + 1);
+        stale.push(fwd);
+        for idx in explicit {
+            self.graph.remove_edge(idx);
+            stale.push(idx);
+        }
+
+        if newly_reversible {
+            // the reversed edge may connect previously-disconnected coordinate systems
+            self.path_cache.clear_mut();
+        } else {
+            self.invalidate_paths(&stale, cheaper);
+        }
+        Ok(with_inverse)
+    }
+
+    /// Set the weight of the explicit edges from `src` to `tgt`,
+    /// which also applies when traversing them in reverse.
+    /// Identity edges keep a weight of 0.
+    ///
+    /// Fails if either coordinate system does not exist,
+    /// or if there is no explicit edge between them.
+    ///
+    /// Cached paths which used these edges are invalidated.
+    /// If the weight decreased, other cached paths are also invalidated,
+    /// as they may now be improved upon.
+    pub fn set_edge_cost<Q1, Q2>(&mut self, src: &Q1, tgt: &Q2, weight: f64) -> Result<(), String>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let (u, v) = self.node_pair(src, tgt)?;
+        let edges = self.edges_with_origin(u, v, EdgeOrigin::Explicit);
+        if edges.is_empty() {
+            return Err("No explicit edge between these coordinate systems".into());
+        }
+
+        let mut cheaper = false;
+        for idx in edges.iter() {
+            let edge = &mut self.graph[*idx];
+            let w = if edge.transform.is_identity() {
+                0.0
+            } else {
+                weight
+            };
+            cheaper |= w < edge.cost.0;
+            edge.cost = OrderedFloat(w);
+        }
+        self.invalidate_paths(&edges, cheaper);
+        Ok(())
+    }
+
+    /// Invalidate cached paths which use any of the given edges.
+    ///
+    /// If any edge has become cheaper, all cached paths are invalidated
+    /// except those known not to exist:
+    /// changing the cost of an edge cannot connect previously-disconnected coordinate systems.
+    fn invalidate_paths(&mut self, edges: &[EdgeIndex], cheaper: bool) {
+        self.path_cache.retain_mut(|_, route| match route {
+            None => true,
+            Some(r) => !cheaper && !r.uses_any(edges),
+        });
+    }
+
+    fn node_pair<Q1, Q2>(&self, src: &Q1, tgt: &Q2) -> Result<(NodeIndex, NodeIndex), String>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let u = self
+            .coord_systems
+            .get(src)
+            .ok_or("Source coordinate system does not exist")?;
+        let v = self
+            .coord_systems
+            .get(tgt)
+            .ok_or("Target coordinate system does not exist")?;
+        Ok((u.idx, v.idx))
+    }
+
+    fn edges_with_origin(&self, u: NodeIndex, v: NodeIndex, origin: EdgeOrigin) -> Vec<EdgeIndex> {
+        self.graph
+            .edges_connecting(u, v)
+            .filter(|e| e.weight().origin == origin)
+            .map(|e| e.id())
+            .collect()
+    }
+
+    /// Get a transformation between two coordinate systems, if it exists.
+    ///
+    /// If the systems are equivalent, an [Identity] will be returned.
+    /// Note that transforming with an [Identity] is a handful of memcpys which could be avoided.
+    ///
+    /// If the two values have a single non-identity edge between them, the transformation of that edge will be returned.
+    /// Longer paths will return a [crate::Sequence].
+    pub fn find_path<Q1, Q2>(&self, from: &Q1, to: &Q2) -> Option<Arc<dyn Transformation>>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let start = self.coord_systems.get(from)?;
+
+        let u = start.idx;
+        let v = self.coord_systems.get(to)?.idx;
+
+        // if the source and target are the same, use an identity
+        if u == v {
+            return Some(Arc::new(Identity::new(start.ndim)));
+        }
+
+        self.route(u, v, start.ndim).map(|r| r.transform.clone())
+    }
+
+    /// As [Self::find_path], but also return the coordinate systems visited
+    /// and the edges traversed, so that the route can be audited.
+    ///
+    /// If the systems are equivalent, the path contains a single coordinate system,
+    /// no edges, and an [Identity].
+    pub fn explain_path<Q1, Q2>(&self, from: &Q1, to: &Q2) -> Option<GraphPath<C>>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let start = self.coord_systems.get(from)?;
+
+        let u = start.idx;
+        let v = self.coord_systems.get(to)?.idx;
+
+        if u == v {
+            return Some(GraphPath::new(
+                vec![self.graph[u].clone()],
+                vec![],
+                Arc::new(Identity::new(start.ndim)),
+            ));
+        }
+
+        let route = self.route(u, v, start.ndim)?;
+        Some(self.graph_path(u, &route.steps, route.transform.clone()))
+    }
+
+    /// List up to `k` alternative loopless paths between two coordinate systems,
+    /// in ascending order of cost, using Yen's algorithm.
+    ///
+    /// Parallel edges between the same coordinate systems,
+    /// and traversing a reversible edge in either direction, produce distinct paths.
+    /// Paths which would traverse an edge whose inverse does not exist are excluded.
+    ///
+    /// If the systems are equivalent, a single path with no edges is returned.
+    /// These paths are not cached.
+    pub fn k_shortest_paths<Q1, Q2>(&self, from: &Q1, to: &Q2, k: usize) -> Vec<GraphPath<C>>
+    where
+        C: Borrow<Q1>,
+        C: Borrow<Q2>,
+        Q1: std::hash::Hash + Eq + ?Sized,
+        Q2: std::hash::Hash + Eq + ?Sized,
+    {
+        let Ok((u, v)) = self.node_pair(from, to) else {
+            return vec![];
+        };
+        if k == 0 {
+            return vec![];
+        }
+        let ndim = self.node_info(u).expect("node exists").ndim;
+        if u == v {
+            return vec![self.graph_path(u, &[], Arc::new(Identity::new(ndim)))];
+        }
+        loop {
+            let paths: Option<Vec<_>> =
+                search::k_shortest_paths(&self.graph, u, v, k, self.inverse_penalty)
+                    .into_iter()
+                    .map(|(_, steps)| {
+                        let t = self.compose(&steps, ndim)?;
+                        Some(self.graph_path(u, &steps, t))
+                    })
+                    .collect();
+            // if an inverse did not exist, it is now known not to, so search again
+            if let Some(p) = paths {
+                return p;
+            }
+        }
+    }
+
+    fn graph_path(
+        &self,
+        u: NodeIndex,
+        steps: &[Step],
+        transform: Arc<dyn Transformation>,
+    ) -> GraphPath<C> {
+        let mut nodes = Vec::with_capacity(steps.len() + 1);
+        nodes.push(self.graph[u].clone());
+        let mut edges = Vec::with_capacity(steps.len());
+        for step in steps {
+            let (_, tgt) = step.endpoints(&self.graph);
+            nodes.push(self.graph[tgt].clone());
+            let edge = &self.graph[step.edge];
+            edges.push(if step.reversed {
+                edge.reversed(self.inverse_penalty)
+                    .expect("inverse was resolved when the path was found")
+            } else {
+                edge.clone()
+            });
+        }
+        GraphPath::new(nodes, edges, transform)
+    }
+
+    /// The transformation of a single step, computing the inverse if necessary.
+    fn step_transform(&self, step: &Step) -> Option<Arc<dyn Transformation>> {
+        let edge = &self.graph[step.edge];
+        if step.reversed {
+            edge.resolve_inverse()
+        } else {
+            Some(edge.transform.clone())
+        }
+    }
+
+    /// Compose the transformations of a non-empty sequence of steps.
+    ///
+    /// Returns None if any reversed step's inverse does not exist.
+    /// All inverses are resolved, so that a repeated search can avoid any which failed.
+    fn compose(&self, steps: &[Step], ndim: usize) -> Option<Arc<dyn Transformation>> {
+        let transforms: Vec<_> = steps.iter().map(|s| self.step_transform(s)).collect();
+        let transforms: Vec<_> = transforms.into_iter().collect::<Option<_>>()?;
+        let t = match transforms.as_slice() {
+            [] => unreachable!("paths between different nodes have steps"),
+            [t] => {
+                // If the edge is an identity, use that for performance.
+                if t.is_identity() {
+                    Arc::new(Identity::new(ndim))
+                } else {
+                    t.clone()
+                }
+            }
+            _ => {
+                let mut builder = SequenceBuilder::with_capacity(transforms.len());
+                for t in transforms {
+                    builder
+                        .add_arced(t)
+                        .expect("already checked dimensionality");
+                }
+                builder
+                    .build_any()
+                    .expect("already checked sequence length")
+            }
+        };
+        Some(t)
+    }
+
+    /// Find the route between two different coordinate systems, using the cache if possible.
+    fn route(&self, u: NodeIndex, v: NodeIndex, ndim: usize) -> Option<Arc<Route>> {
+        // if the path (or lack thereof) is cached, use that
+        if let Some(maybe) = self.path_cache.get(&u, &v) {
+            return maybe;
+        }
+
+        let route = self.find_route(u, v, ndim).map(Arc::new);
+        self.path_cache.insert(u, v, route.clone());
+        route
+    }
+
+    fn find_route(&self, u: NodeIndex, v: NodeIndex, ndim: usize) -> Option<Route> {
+        let no_nodes = HashSet::default();
+        let no_steps = HashSet::default();
+        loop {
+            let (_cost, steps) = search::shortest_path(
+                &self.graph,
+                u,
+                v,
+                self.inverse_penalty,
+                false,
+                &no_nodes,
+                &no_steps,
+            )?;
+            // if an inverse did not exist, it is now known not to, so search again
+            if let Some(transform) = self.compose(&steps, ndim) {
+                return Some(Route { transform, steps });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Axis, EdgeOrigin};
+    use crate::{
+        Matrix, TransformGraph, Transformation,
+        transforms::{Affine, MapAxis, Translate},
+    };
+
+    /// ```text
+    /// a <==> b <==> c
+    ///         \---> d
+    /// ```
+    fn make_graph() -> TransformGraph<&'static str> {
+        let mut tg = TransformGraph::default();
+        tg.add_edge(
+            "a",
+            "b",
+            Arc::new(Translate::try_new(&[1.0, 2.0]).unwrap()),
+            1.0,
+            true,
+        )
+        .unwrap();
+        tg.add_edge(
+            "b",
+            "c",
+            Arc::new(Translate::try_new(&[10.0, 20.0]).unwrap()),
+            1.0,
+            true,
+        )
+        .unwrap();
+        tg.add_edge(
+            "b",
+            "d",
+            Arc::new(Translate::try_new(&[100.0, 200.0]).unwrap()),
+            1.0,
+            false,
+        )
+        .unwrap();
+        tg
+    }
+
+    fn check_transform(t: Arc<dyn Transformation>, input: &[f64], expected: &[f64]) {
+        let mut out_buf = vec![f64::NAN; expected.len()];
+        t.transform_into(input, &mut out_buf);
+        assert_eq!(&out_buf, expected);
+    }
+
+    #[test]
+    fn test_forward() {
+        let tg = make_graph();
+        let t = tg.find_path("a", "c").unwrap();
+        check_transform(t, &[0.0, 0.0], &[11.0, 22.0]);
+
+        let t2 = tg.find_path("a", "d").unwrap();
+        check_transform(t2, &[0.0, 0.0], &[101.0, 202.0]);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let tg = make_graph();
+        let t = tg.find_path("c", "a").unwrap();
+        check_transform(t, &[0.0, 0.0], &[-11.0, -22.0]);
+    }
+
+    #[test]
+    fn test_no_reverse() {
+        let tg = make_graph();
+        assert!(tg.find_path("d", "a").is_none())
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut tg = make_graph();
+        let n_edges = tg.graph.edge_count();
+        tg.add_edge(
+            "a",
+            "b",
+            Arc::new(Translate::try_new(&[1.0, 2.0]).unwrap()),
+            0.5,
+            true,
+        )
+        .unwrap();
+        assert_eq!(tg.graph.edge_count(), n_edges);
+        let a = tg.coord_systems["a"].idx;
+        let b = tg.coord_systems["b"].idx;
+        let edge = tg.graph.edges_connecting(a, b).next().unwrap();
+        assert_eq!(edge.weight().cost(), 0.5);
+        assert!(edge.weight().is_reversible());
+
+        tg.add_edge(
+            "a",
+            "b",
+            Arc::new(Translate::try_new(&[1.0, 3.0]).unwrap()),
+            1.0,
+            false,
+        )
+        .unwrap();
+        assert_eq!(tg.graph.edge_count(), n_edges + 1);
+    }
+
+    fn spatial_axes(names: &str, unit: &str) -> Vec<Axis> {
+        names.chars().map(|c| Axis::space(c, unit)).collect()
+    }
+
+    #[test]
+    fn test_axis_order() {
+        let mut tg = make_graph();
+        tg.add_coordinate_system("xyz", spatial_axes("xyz", "micrometer"))
+            .unwrap();
+        tg.add_coordinate_system("zyx", spatial_axes("zyx", "micrometer"))
+            .unwrap();
+        tg.add_coordinate_system("xyz2", spatial_axes("xyz", "micrometer"))
+            .unwrap();
+        tg.add_coordinate_system("xyz_nm", spatial_axes("xyz", "nanometer"))
+            .unwrap();
+
+        let t = tg.find_path("xyz", "zyx").unwrap();
+        check_transform(t, &[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0]);
+        let t = tg.find_path("xyz2", "zyx").unwrap();
+        check_transform(t, &[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0]);
+
+        // identical axes are not connected, directly or through a re-ordering
+        let xyz = tg.coord_systems["xyz"].idx;
+        let xyz2 = tg.coord_systems["xyz2"].idx;
+        assert!(tg.graph.edges_connecting(xyz, xyz2).next().is_none());
+        assert!(tg.find_path("xyz", "xyz2").is_none());
+        assert!(tg.k_shortest_paths("xyz", "xyz2", 3).is_empty());
+        assert!(
+            !tg.transform_to_all("xyz", &[&[1.0, 2.0, 3.0]])
+                .unwrap()
+                .contains_key("xyz2")
+        );
+
+        // different units are converted
+        let t = tg.find_path("xyz_nm", "zyx").unwrap();
+        check_transform(t, &[1000.0, 2000.0, 3000.0], &[3.0, 2.0, 1.0]);
+        let t = tg.find_path("xyz_nm", "xyz").unwrap();
+        check_transform(t, &[1000.0, 2000.0, 3000.0], &[1.0, 2.0, 3.0]);
+        // but not through to a system with identical axes
+        assert!(tg.find_path("xyz", "xyz2").is_none());
+    }
+
+    #[test]
+    fn test_explicit_axis_mapping() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        tg.add_coordinate_system("xyz", spatial_axes("xyz", "micrometer"))
+            .unwrap();
+        tg.add_coordinate_system("zyx", spatial_axes("zyx", "micrometer"))
+            .unwrap();
+        let n_edges = tg.graph.edge_count();
+
+        // the same as the automatic edge, which becomes explicit
+        tg.add_edge(
+            "xyz",
+            "zyx",
+            Arc::new(MapAxis::try_new(&[2, 1, 0]).unwrap()),
+            0.5,
+            false,
+        )
+        .unwrap();
+        assert_eq!(tg.graph.edge_count(), n_edges);
+        let path = tg.explain_path("xyz", "zyx").unwrap();
+        assert_eq!(path.edges()[0].origin(), EdgeOrigin::Explicit);
+        assert_eq!(path.cost(), 0.5);
+
+        assert!(
+            tg.replace_edge("xyz", "zyx", translate(&[1.0, 1.0, 1.0]), 1.0, false)
+                .is_ok()
+        );
+        assert_eq!(tg.remove_edge("xyz", "zyx"), 1);
+        assert!(tg.find_path("xyz", "zyx").is_none());
+    }
+
+    #[test]
+    fn test_units() {
+        let mut tg = make_graph();
+        tg.add_coordinate_system(
+            "em",
+            vec![
+                Axis::time("t", "millisecond"),
+                Axis::space("y", "nanometer"),
+                Axis::space("x", "nanometer"),
+            ],
+        )
+        .unwrap();
+        tg.add_coordinate_system(
+            "lm",
+            vec![
+                Axis::time("t", "second"),
+                Axis::space("y", "micrometer"),
+                Axis::space("x", "micrometer"),
+            ],
+        )
+        .unwrap();
+        tg.add_coordinate_system(
+            "other",
+            vec![
+                Axis::time("t", "second"),
+                Axis::space("y", "micrometer"),
+                Axis::space("x", "parsnip"),
+            ],
+        )
+        .unwrap();
+
+        let t = tg.find_path("em", "lm").unwrap();
+        check_transform(t, &[1000.0, 10.0, 2000.0], &[1.0, 0.01, 2.0]);
+        let t = tg.find_path("lm", "em").unwrap();
+        check_transform(t, &[1.0, 0.01, 2.0], &[1000.0, 10.0, 2000.0]);
+        assert!(tg.find_path("em", "other").is_none());
+        assert!(tg.add_axis_mapping("em", "other").is_err());
+        assert!(tg.add_axis_mapping("em", "a").is_err());
+    }
+
+    #[test]
+    fn test_axis_validation() {
+        let mut tg = make_graph();
+        assert!(
+            tg.add_coordinate_system("a", spatial_axes("zyx", "micrometer"))
+                .is_err()
+        );
+        tg.add_coordinate_system("a", spatial_axes("yx", "micrometer"))
+            .unwrap();
+        assert!(
+            tg.add_coordinate_system("a", spatial_axes("xy", "micrometer"))
+                .is_err()
+        );
+        assert_eq!(tg.axes("a").unwrap().len(), 2);
+        assert!(tg.axes("b").is_none());
+
+        tg.add_coordinate_system("e", spatial_axes("zyx", "micrometer"))
+            .unwrap();
+        let err = tg.add_edge(
+            "e",
+            "f",
+            Arc::new(Translate::try_new(&[1.0, 2.0]).unwrap()),
+            1.0,
+            false,
+        );
+        assert!(err.is_err());
+    }
+
+    fn translate(t: &[f64]) -> Arc<dyn Transformation> {
+        Arc::new(Translate::try_new(t).unwrap())
+    }
+
+    fn is_cached(tg: &TransformGraph<&'static str>, src: &str, tgt: &str) -> bool {
+        let u = tg.coord_systems[src].idx;
+        let v = tg.coord_systems[tgt].idx;
+        tg.path_cache.get(&u, &v).is_some()
+    }
+
+    #[test]
+    fn test_remove_edge() {
+        let mut tg = make_graph();
+        assert!(tg.find_path("a", "c").is_some());
+        assert!(tg.find_path("a", "d").is_some());
+        assert!(tg.find_path("d", "a").is_none());
+
+        assert_eq!(tg.remove_edge("b", "c"), 1);
+        assert!(!is_cached(&tg, "a", "c"));
+        assert!(is_cached(&tg, "a", "d"));
+        assert!(is_cached(&tg, "d", "a"));
+
+        assert!(tg.find_path("a", "c").is_none());
+        // inverse is also removed
+        assert!(tg.find_path("c", "a").is_none());
+        check_transform(
+            tg.find_path("a", "d").unwrap(),
+            &[0.0, 0.0],
+            &[101.0, 202.0],
+        );
+
+        // reverse traversal cannot be removed on its own
+        assert_eq!(tg.remove_edge("b", "a"), 0);
+        assert!(tg.find_path("b", "a").is_some());
+        assert_eq!(tg.remove_edge("a", "nonexistent"), 0);
+    }
+
+    #[test]
+    fn test_remove_coordinate_system() {
+        let mut tg = make_graph();
+        assert!(tg.find_path("a", "c").is_some());
+        assert!(tg.find_path("c", "b").is_some());
+        assert!(tg.find_path("d", "a").is_none());
+
+        assert!(tg.remove_coordinate_system("b"));
+        assert!(!tg.remove_coordinate_system("b"));
+        assert!(!is_cached(&tg, "a", "c"));
+        assert!(is_cached(&tg, "d", "a"));
+
+        assert!(tg.find_path("a", "c").is_none());
+        assert!(tg.find_path("a", "b").is_none());
+        assert_eq!(tg.graph.edge_count(), 0);
+
+        // a new coordinate system can re-use the name
+        tg.add_edge("b", "c", translate(&[1.0, 1.0]), 1.0, false)
+            .unwrap();
+        check_transform(tg.find_path("b", "c").unwrap(), &[0.0, 0.0], &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_replace_edge() {
+        let mut tg = make_graph();
+        let n_edges = tg.graph.edge_count();
+        assert!(tg.find_path("a", "c").is_some());
+        assert!(tg.find_path("b", "d").is_some());
+
+        assert!(
+            tg.replace_edge("a", "b", translate(&[2.0, 3.0]), 1.0, true)
+                .unwrap()
+        );
+        assert_eq!(tg.graph.edge_count(), n_edges);
+        assert!(!is_cached(&tg, "a", "c"));
+        assert!(is_cached(&tg, "b", "d"));
+        check_transform(tg.find_path("a", "c").unwrap(), &[0.0, 0.0], &[12.0, 23.0]);
+        check_transform(
+            tg.find_path("c", "a").unwrap(),
+            &[0.0, 0.0],
+            &[-12.0, -23.0],
+        );
+
+        // removing the inverse
+        tg.replace_edge("a", "b", translate(&[2.0, 3.0]), 1.0, false)
+            .unwrap();
+        assert_eq!(tg.graph.edge_count(), n_edges);
+        assert!(tg.find_path("c", "a").is_none());
+
+        // only explicit edges can be replaced
+        assert!(
+            tg.replace_edge("c", "b", translate(&[2.0, 3.0]), 1.0, false)
+                .is_err()
+        );
+        assert!(
+            tg.replace_edge("a", "b", translate(&[2.0, 3.0, 4.0]), 1.0, false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_set_edge_cost() {
+        let mut tg = TransformGraph::default();
+        tg.add_edge("a", "b", translate(&[1.0]), 1.0, true).unwrap();
+        tg.add_edge("b", "d", translate(&[1.0]), 1.0, true).unwrap();
+        tg.add_edge("a", "c", translate(&[10.0]), 1.5, true)
+            .unwrap();
+        tg.add_edge("c", "d", translate(&[10.0]), 1.0, true)
+            .unwrap();
+        tg.add_edge("d", "e", translate(&[100.0]), 1.0, false)
+            .unwrap();
+
+        check_transform(tg.find_path("a", "d").unwrap(), &[0.0], &[2.0]);
+        assert!(tg.find_path("e", "a").is_none());
+
+        tg.set_edge_cost("a", "b", 2.0).unwrap();
+        assert!(!is_cached(&tg, "a", "d"));
+        assert!(is_cached(&tg, "e", "a"));
+        check_transform(tg.find_path("a", "d").unwrap(), &[0.0], &[20.0]);
+        check_transform(tg.find_path("d", "a").unwrap(), &[0.0], &[-20.0]);
+
+        tg.set_edge_cost("a", "b", 0.5).unwrap();
+        check_transform(tg.find_path("a", "d").unwrap(), &[0.0], &[2.0]);
+
+        assert!(tg.set_edge_cost("b", "a", 1.0).is_err());
+        assert!(tg.set_edge_cost("a", "z", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_explain_path() {
+        let mut tg = make_graph();
+        tg.set_edge_cost("b", "c", 2.0).unwrap();
+
+        let path = tg.explain_path("c", "a").unwrap();
+        assert_eq!(path.nodes(), &["c", "b", "a"]);
+        assert_eq!(path.edges().len(), 2);
+        assert!(path.edges().iter().all(|e| e.is_inverse()));
+        approx::assert_relative_eq!(path.cost(), 3.2);
+        check_transform(path.transform().clone(), &[0.0, 0.0], &[-11.0, -22.0]);
+
+        let path = tg.explain_path("a", "d").unwrap();
+        let steps: Vec<_> = path
+            .steps()
+            .map(|(src, e, tgt)| (*src, e.origin(), *tgt))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("a", EdgeOrigin::Explicit, "b"),
+                ("b", EdgeOrigin::Explicit, "d")
+            ]
+        );
+
+        let path = tg.explain_path("a", "a").unwrap();
+        assert_eq!(path.nodes(), &["a"]);
+        assert!(path.edges().is_empty());
+        assert_eq!(path.cost(), 0.0);
+
+        assert!(tg.explain_path("d", "a").is_none());
+    }
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let mut tg = make_graph();
+        tg.add_edge("a", "d", translate(&[101.0, 202.0]), 3.0, false)
+            .unwrap();
+        tg.add_edge("c", "d", translate(&[90.0, 180.0]), 1.0, false)
+            .unwrap();
+        tg.add_edge("a", "c", translate(&[11.0, 22.0]), 5.0, false)
+            .unwrap();
+
+        let paths = tg.k_shortest_paths("a", "d", 10);
+        let nodes: Vec<_> = paths.iter().map(|p| p.nodes().to_vec()).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                vec!["a", "b", "d"],
+                vec!["a", "d"],
+                vec!["a", "b", "c", "d"],
+                vec!["a", "c", "d"],
+                vec!["a", "c", "b", "d"],
+            ]
+        );
+        let costs: Vec<_> = paths.iter().map(|p| p.cost()).collect();
+        approx::assert_relative_eq!(costs.as_slice(), [2.0, 3.0, 3.0, 6.0, 7.1].as_slice());
+        for p in paths.iter() {
+            check_transform(p.transform().clone(), &[0.0, 0.0], &[101.0, 202.0]);
+        }
+
+        assert_eq!(tg.k_shortest_paths("a", "d", 2).len(), 2);
+        assert!(tg.k_shortest_paths("d", "a", 2).is_empty());
+        assert_eq!(tg.k_shortest_paths("a", "a", 2).len(), 1);
+    }
+
+    #[test]
+    fn test_path_disagreement() {
+        let mut tg = make_graph();
+        tg.add_edge("a", "d", translate(&[101.0, 203.0]), 3.0, false)
+            .unwrap();
+        let paths = tg.k_shortest_paths("a", "d", 2);
+        let pts: [&[f64]; 2] = [&[0.0, 0.0], &[5.0, 5.0]];
+        assert_eq!(
+            paths[0].disagreement(&paths[1], &pts).unwrap(),
+            vec![1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_lazy_inverse() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        // Affine cannot yet be inverted
+        let matrix = Matrix::try_new(vec![2.0, 0.0, 0.0, 2.0], 2).unwrap();
+        let aff: Arc<dyn Transformation> = Arc::new(Affine::try_new(matrix, &[0.0, 0.0]).unwrap());
+        assert!(tg.add_edge("a", "b", aff, 1.0, true).unwrap());
+        tg.add_edge("a", "c", translate(&[1.0, 1.0]), 1.0, true)
+            .unwrap();
+        tg.add_edge("c", "b", translate(&[1.0, 1.0]), 1.0, true)
+            .unwrap();
+
+        let a = tg.coord_systems["a"].idx;
+        let b = tg.coord_systems["b"].idx;
+        let edge = tg.graph.edges_connecting(a, b).next().unwrap().weight();
+        assert!(edge.is_reversible());
+        assert!(edge.inverse.get().is_none());
+
+        // the cheapest path fails to invert, so the next is found
+        let path = tg.explain_path("b", "a").unwrap();
+        assert_eq!(path.nodes(), &["b", "c", "a"]);
+        check_transform(path.transform().clone(), &[0.0, 0.0], &[-2.0, -2.0]);
+        let edge = tg.graph.edges_connecting(a, b).next().unwrap().weight();
+        assert!(edge.inverse.get().unwrap().is_none());
+
+        // the inverse of the translation is only computed once used
+        let c = tg.coord_systems["c"].idx;
+        let edge = tg.graph.edges_connecting(a, c).next().unwrap().weight();
+        assert!(edge.inverse.get().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_inverse_penalty() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        tg.add_edge("a", "b", translate(&[1.0]), 1.0, true).unwrap();
+        tg.add_edge("b", "a", translate(&[-2.0]), 1.05, false)
+            .unwrap();
+        // by default, the explicit edge is preferred over an inverse of similar cost
+        check_transform(tg.find_path("b", "a").unwrap(), &[0.0], &[-2.0]);
+
+        tg.set_inverse_penalty(0.0);
+        assert_eq!(tg.inverse_penalty(), 0.0);
+        check_transform(tg.find_path("b", "a").unwrap(), &[0.0], &[-1.0]);
+    }
+
+    #[test]
+    fn test_add_edge_estimated() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        tg.add_edge_estimated("a", "b", translate(&[1.0, 2.0]), 0.0, true)
+            .unwrap();
+        tg.add_edge_estimated("a", "c", translate(&[1.0, 1.0]), 10.0, true)
+            .unwrap();
+        tg.add_edge_estimated("c", "b", translate(&[0.0, 1.0]), 0.0, true)
+            .unwrap();
+        let a = tg.coord_systems["a"].idx;
+        let b = tg.coord_systems["b"].idx;
+        let edge = tg.graph.edges_connecting(a, b).next().unwrap().weight();
+        assert_eq!(edge.cost(), 2.0);
+
+        // direct edge is cheaper than two translations with a penalty
+        let path = tg.explain_path("a", "b").unwrap();
+        assert_eq!(path.nodes(), &["a", "b"]);
+        let path = tg.explain_path("c", "b").unwrap();
+        assert_eq!(path.nodes(), &["c", "b"]);
+    }
+}
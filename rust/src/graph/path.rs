@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::{Transformation, graph::Edge, transforms::disagreement};
+
+/// A route through a [crate::TransformGraph], as found by [crate::TransformGraph::explain_path].
+#[derive(Debug, Clone)]
+pub struct GraphPath<C> {
+    nodes: Vec<C>,
+    edges: Vec<Edge>,
+    transform: Arc<dyn Transformation>,
+}
+
+impl<C> GraphPath<C> {
+    pub(crate) fn new(nodes: Vec<C>, edges: Vec<Edge>, transform: Arc<dyn Transformation>) -> Self {
+        debug_assert_eq!(nodes.len(), edges.len() + 1);
+        Self {
+            nodes,
+            edges,
+            transform,
+        }
+    }
+
+    /// The coordinate systems visited, including the source and target.
+    pub fn nodes(&self) -> &[C] {
+        &self.nodes
+    }
+
+    /// The edges traversed, in order.
+    /// Edge `i` goes from node `i` to node `i + 1`.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// The composed transformation, as returned by [crate::TransformGraph::find_path].
+    pub fn transform(&self) -> &Arc<dyn Transformation> {
+        &self.transform
+    }
+
+    /// The total cost of the edges traversed.
+    pub fn cost(&self) -> f64 {
+        self.edges.iter().map(|e| e.cost()).sum()
+    }
+
+    /// Iterate over the steps of the path as (source, edge, target).
+    pub fn steps(&self) -> impl Iterator<Item = (&C, &Edge, &C)> {
+        self.edges
+            .iter()
+            .zip(self.nodes.windows(2))
+            .map(|(e, st)| (&st[0], e, &st[1]))
+    }
+
+    /// The distance between where this path and another put each of the given points.
+    /// See [disagreement].
+    pub fn disagreement(&self, other: &GraphPath<C>, pts: &[&[f64]]) -> Result<Vec<f64>, String> {
+        disagreement(self.transform.as_ref(), other.transform.as_ref(), pts)
+    }
+}
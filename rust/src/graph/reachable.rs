@@ -0,0 +1,154 @@
+use std::{borrow::Borrow, collections::HashMap, sync::Arc};
+
+use crate::{
+    Transformation,
+    graph::{TransformGraph, search::shortest_path_tree},
+};
+
+impl<C: std::hash::Hash + Eq + Clone> TransformGraph<C> {
+    /// Transform points from one coordinate system into every coordinate system reachable from it,
+    /// including itself.
+    ///
+    /// Returns a map from each reachable coordinate system
+    /// to the transformed points as a single row-major buffer,
+    /// i.e. point `i` of an N-dimensional system is at `[i * N..(i + 1) * N]`.
+    ///
+    /// Rather than finding each path separately,
+    /// points are transformed along each edge of the tree of shortest paths from the source,
+    /// so routes which share a prefix only transform along it once.
+    /// The results are therefore the same as transforming with [Self::find_path],
+    /// except where several paths have the same cost.
+    ///
+    /// Fails if the source does not exist or the points do not match its dimensionality.
+    pub fn transform_to_all<Q>(
+        &self,
+        from: &Q,
+        pts: &[&[f64]],
+    ) -> Result<HashMap<C, Vec<f64>>, String>
+    where
+        C: Borrow<Q>,
+        Q: std::hash::Hash + Eq + ?Sized,
+    {
+        let start = self
+            .coord_systems
+            .get(from)
+            .ok_or("Source coordinate system does not exist")?;
+        if let Some(pt) = pts.iter().find(|p| p.len() != start.ndim) {
+            return Err(format!(
+                "Point is {}D; coordinate system is {}D",
+                pt.len(),
+                start.ndim
+            ));
+        }
+
+        let tree = loop {
+            let tree = shortest_path_tree(&self.graph, start.idx, self.inverse_penalty);
+            let transforms: Option<Vec<Arc<dyn Transformation>>> = tree
+                .iter()
+                .map(|ts| self.step_transform(&ts.step))
+                .collect();
+            // if an inverse did not exist, it is now known not to, so search again
+            if let Some(t) = transforms {
+                break tree.into_iter().zip(t).collect::<Vec<_>>();
+            }
+        };
+
+        let src_pts: Vec<f64> = pts.iter().flat_map(|p| p.iter().copied()).collect();
+        // transformed points for each step of the tree
+        let mut tree_pts: Vec<Vec<f64>> = Vec::with_capacity(tree.len());
+        for (ts, t) in tree.iter() {
+            let parent_pts = match ts.parent {
+                Some(p) => &tree_pts[p],
+                None => &src_pts,
+            };
+            let in_ndim = t.input_ndim();
+            let out_ndim = t.output_ndim();
+            let inputs: Vec<&[f64]> = parent_pts.chunks(in_ndim).collect();
+            let mut buf = vec![f64::NAN; pts.len() * out_ndim];
+            {
+                let mut bufs: Vec<&mut [f64]> = buf.chunks_mut(out_ndim).collect();
+                t.bulk_transform_into(&inputs, &mut bufs);
+            }
+            tree_pts.push(buf);
+        }
+
+        let mut out: HashMap<_, Vec<f64>> = HashMap::with_capacity(tree.len() + 1);
+        out.insert(start.idx, src_pts);
+        for ((ts, _), buf) in tree.into_iter().zip(tree_pts) {
+            // the first time a node is reached is the cheapest
+            out.entry(ts.node).or_insert(buf);
+        }
+
+        Ok(out
+            .into_iter()
+            .map(|(idx, v)| (self.graph[idx].clone(), v))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        TransformGraph,
+        transforms::{Scale, Translate},
+    };
+
+    #[test]
+    fn test_transform_to_all() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        tg.add_edge(
+            "a",
+            "b",
+            Arc::new(Translate::try_new(&[1.0, 2.0]).unwrap()),
+            1.0,
+            true,
+        )
+        .unwrap();
+        tg.add_edge(
+            "b",
+            "c",
+            Arc::new(Scale::try_new(&[10.0, 10.0]).unwrap()),
+            1.0,
+            true,
+        )
+        .unwrap();
+        tg.add_edge(
+            "b",
+            "d",
+            Arc::new(Translate::try_new(&[100.0, 200.0]).unwrap()),
+            1.0,
+            false,
+        )
+        .unwrap();
+        tg.add_edge(
+            "e",
+            "f",
+            Arc::new(Translate::try_new(&[1.0, 1.0]).unwrap()),
+            1.0,
+            true,
+        )
+        .unwrap();
+
+        let pts: [&[f64]; 2] = [&[0.0, 0.0], &[1.0, 1.0]];
+        let all = tg.transform_to_all("b", &pts).unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all["a"], vec![-1.0, -2.0, 0.0, -1.0]);
+        assert_eq!(all["b"], vec![0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(all["c"], vec![0.0, 0.0, 10.0, 10.0]);
+        assert_eq!(all["d"], vec![100.0, 200.0, 101.0, 201.0]);
+
+        // consistent with individual paths
+        for (key, vals) in all.iter() {
+            let t = tg.find_path("b", key).unwrap();
+            let mut out = vec![f64::NAN; 2];
+            t.transform_into(pts[1], &mut out);
+            assert_eq!(out.as_slice(), &vals[2..]);
+        }
+
+        assert_eq!(tg.transform_to_all("d", &pts).unwrap().len(), 1);
+        assert!(tg.transform_to_all("z", &pts).is_err());
+        assert!(tg.transform_to_all("a", &[&[1.0]]).is_err());
+    }
+}
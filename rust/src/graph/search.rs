@@ -0,0 +1,295 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use ordered_float::OrderedFloat;
+use petgraph::prelude::*;
+
+use crate::graph::{Edge, EdgeOrigin};
+
+/// A single step along a path: an edge, traversed either forwards or in reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Step {
+    pub edge: EdgeIndex,
+    pub reversed: bool,
+}
+
+impl Step {
+    pub fn forward(edge: EdgeIndex) -> Self {
+        Self {
+            edge,
+            reversed: false,
+        }
+    }
+
+    pub fn reverse(edge: EdgeIndex) -> Self {
+        Self {
+            edge,
+            reversed: true,
+        }
+    }
+
+    /// The (source, target) of this step.
+    pub fn endpoints<N>(&self, graph: &StableDiGraph<N, Edge>) -> (NodeIndex, NodeIndex) {
+        let (src, tgt) = graph.edge_endpoints(self.edge).expect("edge exists");
+        if self.reversed {
+            (tgt, src)
+        } else {
+            (src, tgt)
+        }
+    }
+
+    pub fn cost<N>(&self, graph: &StableDiGraph<N, Edge>, inverse_penalty: f64) -> f64 {
+        let cost = graph[self.edge].cost();
+        if self.reversed {
+            cost + inverse_penalty
+        } else {
+            cost
+        }
+    }
+}
+
+/// A loopless path as a sequence of steps, with its total cost.
+pub(crate) type StepPath = (f64, Vec<Step>);
+
+/// A node, and whether it was reached by an [EdgeOrigin::AxisMapping] edge.
+///
+/// Two such edges are never traversed in a row:
+/// any two compatible re-orderings are already connected directly,
+/// so this would only connect coordinate systems with identical axes,
+/// which are deliberately kept separate.
+type State = (NodeIndex, bool);
+
+fn is_axis_mapping<N>(graph: &StableDiGraph<N, Edge>, step: &Step) -> bool {
+    graph[step.edge].origin == EdgeOrigin::AxisMapping
+}
+
+/// The steps which can be taken from a state, with their target states and costs.
+/// Edges are traversed in reverse if they are reversible and their inverse is not known to fail.
+fn steps_from<N>(
+    graph: &StableDiGraph<N, Edge>,
+    (node, after_axis_mapping): State,
+    inverse_penalty: f64,
+) -> impl Iterator<Item = (Step, State, f64)> {
+    let forward = graph
+        .edges_directed(node, Outgoing)
+        .map(|e| (Step::forward(e.id()), e.target(), e.weight()));
+    let reverse = graph
+        .edges_directed(node, Incoming)
+        .filter(|e| e.weight().can_reverse())
+        .map(|e| (Step::reverse(e.id()), e.source(), e.weight()));
+    forward
+        .chain(reverse)
+        .filter_map(move |(step, next, edge)| {
+            let axis_mapping = edge.origin == EdgeOrigin::AxisMapping;
+            if after_axis_mapping && axis_mapping {
+                return None;
+            }
+            Some((
+                step,
+                (next, axis_mapping),
+                step.cost(graph, inverse_penalty),
+            ))
+        })
+}
+
+/// Dijkstra's algorithm which ignores the given nodes and steps.
+///
+/// `after_axis_mapping` is whether `src` was reached by an axis mapping edge,
+/// in which case the path cannot start with another (see [State]).
+///
+/// Returns the cost and steps of the cheapest path from `src` to `tgt`, if one exists.
+pub(crate) fn shortest_path<N>(
+    graph: &StableDiGraph<N, Edge>,
+    src: NodeIndex,
+    tgt: NodeIndex,
+    inverse_penalty: f64,
+    after_axis_mapping: bool,
+    excluded_nodes: &HashSet<NodeIndex>,
+    excluded_steps: &HashSet<Step>,
+) -> Option<StepPath> {
+    let start = (src, after_axis_mapping);
+    let mut dist: HashMap<State, OrderedFloat<f64>> = HashMap::default();
+    let mut pred: HashMap<State, (Step, State)> = HashMap::default();
+    let mut heap = BinaryHeap::new();
+    dist.insert(start, OrderedFloat(0.0));
+    heap.push(Reverse((OrderedFloat(0.0), start)));
+
+    while let Some(Reverse((cost, state))) = heap.pop() {
+        if state.0 == tgt {
+            let mut steps = vec![];
+            let mut current = state;
+            while current != start {
+                let (step, prev) = pred[&current];
+                steps.push(step);
+                current = prev;
+            }
+            steps.reverse();
+            return Some((cost.0, steps));
+        }
+        if dist.get(&state).is_some_and(|d| *d < cost) {
+            // stale heap entry
+            continue;
+        }
+        for (step, next, step_cost) in steps_from(graph, state, inverse_penalty) {
+            if next.0 == src || excluded_steps.contains(&step) || excluded_nodes.contains(&next.0) {
+                continue;
+            }
+            let next_cost = cost + step_cost;
+            if dist.get(&next).is_none_or(|d| next_cost < *d) {
+                dist.insert(next, next_cost);
+                pred.insert(next, (step, state));
+                heap.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+    None
+}
+
+/// A step in a tree of shortest paths.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TreeStep {
+    pub step: Step,
+    /// The position in the tree of the step which reached this one's source,
+    /// or None if that is the root.
+    pub parent: Option<usize>,
+    pub node: NodeIndex,
+}
+
+/// Dijkstra's algorithm from `src` to every reachable node.
+///
+/// Returns the steps of the shortest-path tree in order of increasing cost from `src`;
+/// so each step's parent comes before it.
+/// A node may be reached twice, with and without a preceding axis mapping edge (see [State]);
+/// the first is the cheapest.
+pub(crate) fn shortest_path_tree<N>(
+    graph: &StableDiGraph<N, Edge>,
+    src: NodeIndex,
+    inverse_penalty: f64,
+) -> Vec<TreeStep> {
+    let start = (src, false);
+    let mut dist: HashMap<State, OrderedFloat<f64>> = HashMap::default();
+    let mut pred: HashMap<State, (Step, State)> = HashMap::default();
+    let mut settled: HashMap<State, Option<usize>> = HashMap::default();
+    let mut heap = BinaryHeap::new();
+    let mut out = vec![];
+    dist.insert(start, OrderedFloat(0.0));
+    heap.push(Reverse((OrderedFloat(0.0), start)));
+
+    while let Some(Reverse((cost, state))) = heap.pop() {
+        if settled.contains_key(&state) {
+            continue;
+        }
+        let position = pred.get(&state).map(|(step, prev)| {
+            out.push(TreeStep {
+                step: *step,
+                parent: settled[prev],
+                node: state.0,
+            });
+            out.len() - 1
+        });
+        settled.insert(state, position);
+        for (step, next, step_cost) in steps_from(graph, state, inverse_penalty) {
+            if next.0 == src || settled.contains_key(&next) {
+                continue;
+            }
+            let next_cost = cost + step_cost;
+            if dist.get(&next).is_none_or(|d| next_cost < *d) {
+                dist.insert(next, next_cost);
+                pred.insert(next, (step, state));
+                heap.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+    out
+}
+
+/// Yen's algorithm for the `k` cheapest loopless paths from `src` to `tgt`,
+/// in ascending order of cost.
+///
+/// Parallel edges, and traversing an edge forwards or in reverse, are treated as distinct paths.
+pub(crate) fn k_shortest_paths<N>(
+    graph: &StableDiGraph<N, Edge>,
+    src: NodeIndex,
+    tgt: NodeIndex,
+    k: usize,
+    inverse_penalty: f64,
+) -> Vec<StepPath> {
+    let mut found: Vec<StepPath> = Vec::with_capacity(k);
+    if k == 0 {
+        return found;
+    }
+    let Some(first) = shortest_path(
+        graph,
+        src,
+        tgt,
+        inverse_penalty,
+        false,
+        &HashSet::default(),
+        &HashSet::default(),
+    ) else {
+        return found;
+    };
+    found.push(first);
+    let mut candidates: Vec<StepPath> = vec![];
+
+    while found.len() < k {
+        let prev = &found.last().expect("at least one path").1;
+        let nodes = path_nodes(graph, src, prev);
+
+        for spur_idx in 0..prev.len() {
+            let spur_node = nodes[spur_idx];
+            let root = &prev[..spur_idx];
+
+            // don't repeat the next step of any known path sharing this root
+            let excluded_steps: HashSet<_> = found
+                .iter()
+                .filter(|(_, p)| p.len() > spur_idx && &p[..spur_idx] == root)
+                .map(|(_, p)| p[spur_idx])
+                .collect();
+            // keep the path loopless
+            let excluded_nodes: HashSet<_> = nodes[..spur_idx].iter().copied().collect();
+
+            let after_axis_mapping = root.last().is_some_and(|s| is_axis_mapping(graph, s));
+            let Some((_, spur)) = shortest_path(
+                graph,
+                spur_node,
+                tgt,
+                inverse_penalty,
+                after_axis_mapping,
+                &excluded_nodes,
+                &excluded_steps,
+            ) else {
+                continue;
+            };
+            let mut steps = root.to_vec();
+            steps.extend(spur);
+            if candidates.iter().any(|(_, p)| p == &steps) {
+                continue;
+            }
+            let cost = steps.iter().map(|s| s.cost(graph, inverse_penalty)).sum();
+            candidates.push((cost, steps));
+        }
+
+        // cheapest candidate, preferring fewer steps
+        let Some(best) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (cost, steps))| (OrderedFloat(*cost), steps.len()))
+            .map(|(idx, _)| idx)
+        else {
+            break;
+        };
+        found.push(candidates.swap_remove(best));
+    }
+    found
+}
+
+/// The nodes visited by a path, including the start and end.
+fn path_nodes<N>(graph: &StableDiGraph<N, Edge>, src: NodeIndex, steps: &[Step]) -> Vec<NodeIndex> {
+    let mut nodes = Vec::with_capacity(steps.len() + 1);
+    nodes.push(src);
+    nodes.extend(steps.iter().map(|s| s.endpoints(graph).1));
+    nodes
+}
@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use crate::{
+    Transformation,
+    graph::{Axis, DEFAULT_COST, EdgeOrigin, TransformGraph},
+    matrix::Matrix,
+    transforms::Affine,
+};
+
+/// Fails if any axis index is repeated or out of range.
+fn validate_subspace_axes(axes: &[usize], parent_ndim: usize) -> Result<(), String> {
+    if axes.len() > parent_ndim {
+        return Err(format!(
+            "Subspace has {} axes; parent is {}D",
+            axes.len(),
+            parent_ndim
+        ));
+    }
+    let mut seen = vec![false; parent_ndim];
+    for ax in axes {
+        let Some(s) = seen.get_mut(*ax) else {
+            return Err(format!("Axis {ax} out of range for {parent_ndim}D parent"));
+        };
+        if *s {
+            return Err(format!("Axis {ax} is repeated"));
+        }
+        *s = true;
+    }
+    Ok(())
+}
+
+/// Select the given axes of the parent space, relative to the offset.
+fn projection(parent_ndim: usize, axes: &[usize], offset: &[f64]) -> Affine {
+    let mut matrix = Matrix::new_zeros(axes.len(), parent_ndim);
+    let mut translation = Vec::with_capacity(axes.len());
+    for (row, ax) in axes.iter().enumerate() {
+        matrix[(row, *ax)] = 1.0;
+        translation.push(-offset[*ax]);
+    }
+    Affine::try_new(matrix, &translation).expect("dimensions are consistent")
+}
+
+/// Place the subspace's axes on the given axes of the parent space, at the offset.
+fn embedding(parent_ndim: usize, axes: &[usize], offset: &[f64]) -> Affine {
+    let mut matrix = Matrix::new_zeros(parent_ndim, axes.len());
+    for (col, ax) in axes.iter().enumerate() {
+        matrix[(*ax, col)] = 1.0;
+    }
+    Affine::try_new(matrix, offset).expect("dimensions are consistent")
+}
+
+impl<C: std::hash::Hash + Eq + Clone> TransformGraph<C> {
+    /// Declare that `sub` is made up of the given axes of the existing coordinate system `parent`,
+    /// adding `sub` if it does not already exist.
+    /// Axis `i` of `sub` is axis `axes[i]` of `parent`.
+    ///
+    /// An edge which projects `parent` onto `sub` is added.
+    /// No edge is added from `sub` to `parent`, as the other axes' values are unknown;
+    /// see [Self::add_embedding].
+    ///
+    /// If `parent` has declared axes and `sub` does not,
+    /// `sub` takes the selected axes (see [Self::add_coordinate_system]).
+    ///
+    /// Fails if `parent` does not exist, if axes are repeated or out of range,
+    /// if `sub` already exists with a different dimensionality,
+    /// or if both have declared axes whose names, types or units do not match.
+    /// Units are not converted; to relate axes in different units,
+    /// add a subspace in the parent's units and an edge or
+    /// [axis mapping](Self::add_axis_mapping) from it.
+    ///
+    /// Adding a subspace clears all cached paths.
+    pub fn add_subspace(
+        &mut self,
+        sub: impl Into<C>,
+        parent: impl Into<C>,
+        axes: &[usize],
+    ) -> Result<(), String> {
+        self.add_subspace_edges(sub.into(), parent.into(), axes, None)
+    }
+
+    /// Declare that `sub` is embedded in the existing coordinate system `parent`,
+    /// with axis `i` of `sub` along axis `axes[i]` of `parent`,
+    /// and its origin at `offset` in `parent`.
+    /// For example, a 2D plane at `z = 5` in a ZYX volume
+    /// has axes `[1, 2]` and offset `[5, 0, 0]`.
+    ///
+    /// Edges are added in both directions:
+    /// the embedding from `sub` to `parent`, and the projection from `parent` to `sub`.
+    ///
+    /// Otherwise, as [Self::add_subspace].
+    /// Also fails if the offset does not match the dimensionality of `parent`.
+    pub fn add_embedding(
+        &mut self,
+        sub: impl Into<C>,
+        parent: impl Into<C>,
+        axes: &[usize],
+        offset: &[f64],
+    ) -> Result<(), String> {
+        self.add_subspace_edges(sub.into(), parent.into(), axes, Some(offset))
+    }
+
+    fn add_subspace_edges(
+        &mut self,
+        sub: C,
+        parent: C,
+        axes: &[usize],
+        offset: Option<&[f64]>,
+    ) -> Result<(), String> {
+        let parent_info = self
+            .coord_systems
+            .get(&parent)
+            .ok_or("Parent coordinate system does not exist")?;
+        let parent_ndim = parent_info.ndim;
+        let v = parent_info.idx;
+        validate_subspace_axes(axes, parent_ndim)?;
+        if let Some(o) = offset
+            && o.len() != parent_ndim
+        {
+            return Err(format!(
+                "Offset is {}D; parent is {}D",
+                o.len(),
+                parent_ndim
+            ));
+        }
+        let selected: Option<Vec<Axis>> = parent_info
+            .axes
+            .as_ref()
+            .map(|parent_axes| axes.iter().map(|ax| parent_axes[*ax].clone()).collect());
+        if let Some(selected) = selected.as_ref()
+            && let Some(sub_axes) = self.axes(&sub)
+            && sub_axes
+                .iter()
+                .map(|a| (a.name(), a.axis_type(), a.unit()))
+                .ne(selected.iter().map(|a| (a.name(), a.axis_type(), a.unit())))
+        {
+            return Err("Subspace axis names, types or units do not match the parent's".into());
+        }
+
+        let u = self.ensure_coord_system(sub.clone(), axes.len())?;
+        if u == v {
+            return Err("Coordinate system cannot be a subspace of itself".into());
+        }
+        self.path_cache.clear_mut();
+
+        let zeros = vec![0.0; parent_ndim];
+        let proj: Arc<dyn Transformation> =
+            Arc::new(projection(parent_ndim, axes, offset.unwrap_or(&zeros)));
+        self.add_or_merge_edge(v, u, proj, DEFAULT_COST, EdgeOrigin::Subspace, false);
+        if let Some(offset) = offset {
+            let emb: Arc<dyn Transformation> = Arc::new(embedding(parent_ndim, axes, offset));
+            self.add_or_merge_edge(u, v, emb, DEFAULT_COST, EdgeOrigin::Subspace, false);
+        }
+
+        if let Some(selected) = selected
+            && self.coord_systems[&sub].axes.is_none()
+        {
+            self.add_coordinate_system(sub, selected)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_path(
+        tg: &TransformGraph<&str>,
+        from: &str,
+        to: &str,
+        input: &[f64],
+        expected: &[f64],
+    ) {
+        let t = tg.find_path(from, to).unwrap();
+        let mut out = vec![f64::NAN; expected.len()];
+        t.transform_into(input, &mut out);
+        assert_eq!(out.as_slice(), expected);
+    }
+
+    #[test]
+    fn test_subspace_embedding() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        tg.add_coordinate_system(
+            "czyx",
+            vec![
+                Axis::channel("c"),
+                Axis::space("z", "micrometer"),
+                Axis::space("y", "micrometer"),
+                Axis::space("x", "micrometer"),
+            ],
+        )
+        .unwrap();
+        tg.add_subspace("zyx", "czyx", &[1, 2, 3]).unwrap();
+        tg.add_embedding("yx", "zyx", &[1, 2], &[5.0, 0.0, 0.0])
+            .unwrap();
+
+        check_path(&tg, "czyx", "zyx", &[0.0, 1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]);
+        check_path(&tg, "yx", "zyx", &[2.0, 3.0], &[5.0, 2.0, 3.0]);
+        check_path(&tg, "zyx", "yx", &[5.0, 2.0, 3.0], &[2.0, 3.0]);
+        check_path(&tg, "czyx", "yx", &[0.0, 5.0, 2.0, 3.0], &[2.0, 3.0]);
+        assert!(tg.find_path("zyx", "czyx").is_none());
+        assert!(tg.find_path("yx", "czyx").is_none());
+
+        // axes are inherited
+        let names: Vec<_> = tg.axes("yx").unwrap().iter().map(|a| a.name()).collect();
+        assert_eq!(names, vec!["y", "x"]);
+    }
+
+    #[test]
+    fn test_subspace_validation() {
+        let mut tg: TransformGraph<&str> = TransformGraph::default();
+        tg.add_coordinate_system(
+            "zyx",
+            vec![
+                Axis::space("z", "micrometer"),
+                Axis::space("y", "micrometer"),
+                Axis::space("x", "micrometer"),
+            ],
+        )
+        .unwrap();
+        assert!(tg.add_subspace("yx", "missing", &[1, 2]).is_err());
+        assert!(tg.add_subspace("yx", "zyx", &[1, 1]).is_err());
+        assert!(tg.add_subspace("yx", "zyx", &[1, 3]).is_err());
+        assert!(tg.add_subspace("zyx", "zyx", &[0, 1, 2]).is_err());
+        assert!(tg.add_embedding("yx", "zyx", &[1, 2], &[5.0, 0.0]).is_err());
+        tg.add_coordinate_system(
+            "yx",
+            vec![
+                Axis::space("z", "micrometer"),
+                Axis::space("x", "micrometer"),
+            ],
+        )
+        .unwrap();
+        assert!(tg.add_subspace("yx", "zyx", &[1, 2]).is_err());
+        tg.add_subspace("yx", "zyx", &[0, 2]).unwrap();
+
+        // same names, different units
+        tg.add_coordinate_system(
+            "yx_nm",
+            vec![Axis::space("y", "nanometer"), Axis::space("x", "nanometer")],
+        )
+        .unwrap();
+        assert!(tg.add_subspace("yx_nm", "zyx", &[1, 2]).is_err());
+        assert!(tg.find_path("zyx", "yx_nm").is_none());
+
+        // same names and units, different types
+        tg.add_coordinate_system(
+            "tx",
+            vec![
+                Axis::time("y", "micrometer"),
+                Axis::space("x", "micrometer"),
+            ],
+        )
+        .unwrap();
+        assert!(tg.add_subspace("tx", "zyx", &[1, 2]).is_err());
+        assert!(tg.find_path("zyx", "tx").is_none());
+    }
+}
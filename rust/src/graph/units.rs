@@ -0,0 +1,130 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Time,
+}
+
+/// SI prefixes as powers of 10.
+const PREFIXES: [(&str, i32); 21] = [
+    ("yotta", 24),
+    ("zetta", 21),
+    ("exa", 18),
+    ("peta", 15),
+    ("tera", 12),
+    ("giga", 9),
+    ("mega", 6),
+    ("kilo", 3),
+    ("hecto", 2),
+    ("deca", 1),
+    ("", 0),
+    ("deci", -1),
+    ("centi", -2),
+    ("milli", -3),
+    ("micro", -6),
+    ("nano", -9),
+    ("pico", -12),
+    ("femto", -15),
+    ("atto", -18),
+    ("zepto", -21),
+    ("yocto", -24),
+];
+
+/// Units which are not an SI prefix on meter or second,
+/// as (name, dimension, power of 10, multiplier) of the base unit.
+const OTHER_UNITS: [(&str, Dimension, i32, f64); 9] = [
+    ("angstrom", Dimension::Length, -10, 1.0),
+    ("inch", Dimension::Length, 0, 0.0254),
+    ("foot", Dimension::Length, 0, 0.3048),
+    ("yard", Dimension::Length, 0, 0.9144),
+    ("mile", Dimension::Length, 0, 1609.344),
+    ("parsec", Dimension::Length, 0, 3.085_677_581_491_367e16),
+    ("minute", Dimension::Time, 0, 60.0),
+    ("hour", Dimension::Time, 0, 3600.0),
+    ("day", Dimension::Time, 0, 86400.0),
+];
+
+/// Look up a unit's dimension, power of 10, and multiplier relative to meters or seconds.
+fn lookup(unit: &str) -> Option<(Dimension, i32, f64)> {
+    for (base, dim) in [("meter", Dimension::Length), ("second", Dimension::Time)] {
+        if let Some(prefix) = unit.strip_suffix(base)
+            && let Some((_, exp)) = PREFIXES.iter().find(|(p, _)| *p == prefix)
+        {
+            return Some((dim, *exp, 1.0));
+        }
+    }
+    OTHER_UNITS
+        .iter()
+        .find(|(name, ..)| *name == unit)
+        .map(|(_, dim, exp, mult)| (*dim, *exp, *mult))
+}
+
+/// Exact (where possible) power of 10.
+fn pow10(exp: i32) -> f64 {
+    if exp < 0 {
+        1.0 / 10f64.powi(-exp)
+    } else {
+        10f64.powi(exp)
+    }
+}
+
+/// The factor by which to multiply a value in unit `from` to get a value in unit `to`.
+///
+/// Supports the UDUNITS-2 names of the length and time units used in OME-Zarr,
+/// e.g. `"nanometer"`, `"micrometer"`, `"millisecond"`, `"second"`.
+/// Returns `None` if either unit is unknown, or if they measure different quantities.
+pub fn unit_conversion_factor(from: &str, to: &str) -> Option<f64> {
+    if from == to {
+        return Some(1.0);
+    }
+    let (from_dim, from_exp, from_mult) = lookup(from)?;
+    let (to_dim, to_exp, to_mult) = lookup(to)?;
+    if from_dim != to_dim {
+        return None;
+    }
+    Some(from_mult / to_mult * pow10(from_exp - to_exp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unit_conversion_factor;
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(
+            unit_conversion_factor("nanometer", "micrometer"),
+            Some(1e-3)
+        );
+        assert_eq!(unit_conversion_factor("micrometer", "nanometer"), Some(1e3));
+        assert_eq!(unit_conversion_factor("millisecond", "second"), Some(1e-3));
+        assert_eq!(unit_conversion_factor("meter", "kilometer"), Some(1e-3));
+        assert_eq!(unit_conversion_factor("angstrom", "nanometer"), Some(0.1));
+        assert_eq!(
+            unit_conversion_factor("kilosecond", "millisecond"),
+            Some(1e6)
+        );
+    }
+
+    #[test]
+    fn test_other() {
+        assert_eq!(unit_conversion_factor("hour", "minute"), Some(60.0));
+        assert_eq!(unit_conversion_factor("day", "second"), Some(86400.0));
+        approx::assert_relative_eq!(
+            unit_conversion_factor("foot", "inch").unwrap(),
+            12.0,
+            max_relative = 1e-15
+        );
+        approx::assert_relative_eq!(
+            unit_conversion_factor("inch", "millimeter").unwrap(),
+            25.4,
+            max_relative = 1e-15
+        );
+    }
+
+    #[test]
+    fn test_incompatible() {
+        assert!(unit_conversion_factor("meter", "second").is_none());
+        assert!(unit_conversion_factor("meter", "furlong").is_none());
+        assert!(unit_conversion_factor("nm", "meter").is_none());
+        assert_eq!(unit_conversion_factor("furlong", "furlong"), Some(1.0));
+    }
+}
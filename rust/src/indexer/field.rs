@@ -0,0 +1,450 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{
+    ArrayProvider, ShortVec,
+    indexer::value::{
+        BSpline, BoundedIndex, CatmullRom, Clamp, Const, Lanczos, Linear, Mirror, NearestNeighbour,
+        Numeric, RealIndex, Reflect, SplineCoefficients, UnboundedIndex, Wrap,
+    },
+};
+
+/// Which axis of an array holds the components of each vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorAxis {
+    First,
+    Last,
+}
+
+/// How values are interpolated between array elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    Nearest,
+    #[default]
+    Linear,
+    CatmullRom,
+    /// Cubic B-spline; the array is prefiltered when the field is created.
+    /// The prefilter assumes mirrored edges, so only [Boundary::Mirror] is supported.
+    CubicBSpline,
+    /// Lanczos with the given radius.
+    Lanczos(usize),
+}
+
+/// How values are extended beyond the edges of an array.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    /// Fill with a constant value.
+    Constant(f64),
+    /// Repeat the edge element.
+    Clamp,
+    /// Reflect about the edge, repeating the edge element.
+    Reflect,
+    /// Reflect about the edge element, without repeating it.
+    Mirror,
+    /// Tile periodically.
+    Wrap,
+}
+
+type ComponentIndex = Box<dyn RealIndex<f64> + Send + Sync>;
+
+/// One component of each vector in an array, as an array of one fewer dimension.
+struct ComponentView<T, B: BoundedIndex<T>> {
+    array: Arc<B>,
+    vector_axis: VectorAxis,
+    component: usize,
+    extents: Vec<usize>,
+    _t: PhantomData<T>,
+}
+
+impl<T, B: BoundedIndex<T>> ComponentView<T, B> {
+    fn new(array: Arc<B>, vector_axis: VectorAxis, component: usize) -> Self {
+        let mut extents = array.extents().to_vec();
+        match vector_axis {
+            VectorAxis::First => extents.remove(0),
+            VectorAxis::Last => extents.pop().expect("array has dimensions"),
+        };
+        Self {
+            array,
+            vector_axis,
+            component,
+            extents,
+            _t: Default::default(),
+        }
+    }
+
+    fn array_coord(&self, coord: &[usize]) -> ShortVec<usize> {
+        let mut out = ShortVec::with_capacity(coord.len() + 1);
+        if self.vector_axis == VectorAxis::First {
+            out.push(self.component);
+        }
+        out.extend_from_slice(coord);
+        if self.vector_axis == VectorAxis::Last {
+            out.push(self.component);
+        }
+        out
+    }
+}
+
+impl<T: Numeric, B: BoundedIndex<T>> BoundedIndex<f64> for ComponentView<T, B> {
+    fn get(&self, coord: &[usize]) -> Option<f64> {
+        self.array.get(&self.array_coord(coord)).map(|v| v.to_f64())
+    }
+
+    fn get_unchecked(&self, coord: &[usize]) -> f64 {
+        self.array.get_unchecked(&self.array_coord(coord)).to_f64()
+    }
+
+    fn column_get_into_unchecked(&self, columns: &[&[usize]], buf: &mut [f64]) {
+        let component = vec![self.component; buf.len()];
+        let mut array_columns = Vec::with_capacity(columns.len() + 1);
+        if self.vector_axis == VectorAxis::First {
+            array_columns.push(component.as_slice());
+        }
+        array_columns.extend_from_slice(columns);
+        if self.vector_axis == VectorAxis::Last {
+            array_columns.push(component.as_slice());
+        }
+        let mut values = vec![T::default(); buf.len()];
+        self.array
+            .column_get_into_unchecked(&array_columns, &mut values);
+        for (b, v) in buf.iter_mut().zip(values) {
+            *b = v.to_f64();
+        }
+    }
+
+    fn extents(&self) -> &[usize] {
+        &self.extents
+    }
+}
+
+/// Interpolate an unbounded component.
+fn interpolate<U>(unbounded: U, interpolation: Interpolation) -> Result<ComponentIndex, String>
+where
+    U: UnboundedIndex<f64> + Send + Sync + 'static,
+{
+    Ok(match interpolation {
+        Interpolation::Nearest => Box::new(NearestNeighbour::new(unbounded)),
+        Interpolation::Linear => Box::new(Linear::new(unbounded)),
+        Interpolation::CatmullRom => Box::new(CatmullRom::new(unbounded)),
+        Interpolation::CubicBSpline => Box::new(BSpline::new(unbounded)),
+        Interpolation::Lanczos(radius) => Box::new(Lanczos::try_new(unbounded, radius)?),
+    })
+}
+
+/// Extend a bounded component and interpolate it.
+fn extend<A>(
+    bounded: A,
+    boundary: Boundary,
+    interpolation: Interpolation,
+) -> Result<ComponentIndex, String>
+where
+    A: BoundedIndex<f64> + Send + Sync + 'static,
+{
+    match boundary {
+        Boundary::Constant(c) => interpolate(Const::new(bounded, c), interpolation),
+        Boundary::Clamp => interpolate(Clamp::try_new(bounded)?, interpolation),
+        Boundary::Reflect => interpolate(Reflect::try_new(bounded)?, interpolation),
+        Boundary::Mirror => interpolate(Mirror::try_new(bounded)?, interpolation),
+        Boundary::Wrap => interpolate(Wrap::try_new(bounded)?, interpolation),
+    }
+}
+
+/// An [ArrayProvider] which interpolates a field of vectors stored in an array,
+/// e.g. for [Coordinate](crate::transforms::Coordinate) and [Displacement](crate::transforms::Displacement) transforms.
+///
+/// The array has one more dimension than the field: the [VectorAxis] holds the components of each vector.
+/// Points are given in array index space, i.e. the coordinate of element `[i, j]` is `[i, j]`.
+pub struct ArrayField {
+    components: Vec<ComponentIndex>,
+    extents: Vec<usize>,
+    vector_axis: VectorAxis,
+    interpolation: Interpolation,
+    boundary: Boundary,
+}
+
+impl ArrayField {
+    pub fn try_new<T, B>(
+        array: B,
+        vector_axis: VectorAxis,
+        interpolation: Interpolation,
+        boundary: Boundary,
+    ) -> Result<Self, String>
+    where
+        T: Numeric + Send + Sync + 'static,
+        B: BoundedIndex<T> + Send + Sync + 'static,
+    {
+        if array.ndim() < 2 {
+            return Err("Vector field array must have at least 2 dimensions".into());
+        }
+        if interpolation == Interpolation::CubicBSpline && boundary != Boundary::Mirror {
+            return Err("Cubic B-spline interpolation requires the mirror boundary".into());
+        }
+        let array = Arc::new(array);
+        let n_components = match vector_axis {
+            VectorAxis::First => array.extents()[0],
+            VectorAxis::Last => *array.extents().last().expect("array has dimensions"),
+        };
+        if n_components == 0 {
+            return Err("Vector field has no components".into());
+        }
+
+        let mut components = Vec::with_capacity(n_components);
+        for component in 0..n_components {
+            let view = ComponentView::new(array.clone(), vector_axis, component);
+            let index = if interpolation == Interpolation::CubicBSpline {
+                extend(
+                    SplineCoefficients::from_bounded(&view),
+                    boundary,
+                    interpolation,
+                )?
+            } else {
+                extend(view, boundary, interpolation)?
+            };
+            components.push(index);
+        }
+        let extents = ComponentView::new(array, vector_axis, 0).extents;
+
+        Ok(Self {
+            components,
+            extents,
+            vector_axis,
+            interpolation,
+            boundary,
+        })
+    }
+
+    /// The shape of the field, excluding the vector axis.
+    pub fn extents(&self) -> &[usize] {
+        &self.extents
+    }
+
+    pub fn vector_axis(&self) -> VectorAxis {
+        self.vector_axis
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+}
+
+impl std::fmt::Debug for ArrayField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArrayField")
+            .field("extents", &self.extents)
+            .field("n_components", &self.components.len())
+            .field("vector_axis", &self.vector_axis)
+            .field("interpolation", &self.interpolation)
+            .field("boundary", &self.boundary)
+            .finish()
+    }
+}
+
+impl ArrayProvider for ArrayField {
+    fn get_into(&self, pt: &[f64], buf: &mut [f64]) {
+        for (b, component) in buf.iter_mut().zip(self.components.iter()) {
+            *b = component.get(pt);
+        }
+    }
+
+    fn bulk_get_into(&self, pts: &[&[f64]], bufs: &mut [&mut [f64]]) {
+        let mut values = vec![f64::NAN; pts.len()];
+        for (idx, component) in self.components.iter().enumerate() {
+            component.bulk_get_into(pts, &mut values);
+            for (buf, v) in bufs.iter_mut().zip(values.iter()) {
+                buf[idx] = *v;
+            }
+        }
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], bufs: &mut [&mut [f64]]) {
+        for (buf, component) in bufs.iter_mut().zip(self.components.iter()) {
+            component.column_get_into(columns, buf);
+        }
+    }
+
+    fn index_len(&self) -> usize {
+        self.extents.len()
+    }
+
+    fn output_len(&self) -> usize {
+        self.components.len()
+    }
+
+    fn domain(&self) -> Option<&[usize]> {
+        Some(&self.extents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+    use crate::{
+        Transformation,
+        ndarr::{RowMajor, VecNdArray},
+        transforms::{Displacement, OutOfDomain},
+    };
+
+    /// A 3x4 field of 2D vectors `[y / 2, x]`, vector axis last.
+    fn field_data() -> VecNdArray<f32, RowMajor> {
+        let mut data = vec![];
+        for y in 0..3 {
+            for x in 0..4 {
+                data.extend([y as f32 / 2.0, x as f32]);
+            }
+        }
+        VecNdArray::new(data, RowMajor::new(&[3, 4, 2])).unwrap()
+    }
+
+    #[test]
+    fn test_linear() {
+        let field = ArrayField::try_new(
+            field_data(),
+            VectorAxis::Last,
+            Interpolation::Linear,
+            Boundary::Clamp,
+        )
+        .unwrap();
+        assert_eq!(field.extents(), &[3, 4]);
+        assert_eq!(field.output_len(), 2);
+
+        let mut buf = [f64::NAN; 2];
+        field.get_into(&[1.5, 2.25], &mut buf);
+        assert_abs_diff_eq!(buf.as_slice(), [0.75, 2.25].as_slice(), epsilon = 1e-12);
+        // clamped beyond the edge
+        field.get_into(&[-1.0, 5.0], &mut buf);
+        assert_abs_diff_eq!(buf.as_slice(), [0.0, 3.0].as_slice(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_paths_agree() {
+        let field = ArrayField::try_new(
+            field_data(),
+            VectorAxis::Last,
+            Interpolation::CatmullRom,
+            Boundary::Constant(0.0),
+        )
+        .unwrap();
+        let pts = [[0.5, 0.5], [1.25, 2.75], [2.0, 3.5]];
+
+        let mut expected = vec![[f64::NAN; 2]; pts.len()];
+        for (pt, e) in pts.iter().zip(expected.iter_mut()) {
+            field.get_into(pt, e);
+        }
+
+        let pt_refs: Vec<&[f64]> = pts.iter().map(|p| p.as_slice()).collect();
+        let mut bulk = vec![[f64::NAN; 2]; pts.len()];
+        let mut bulk_refs: Vec<&mut [f64]> = bulk.iter_mut().map(|b| b.as_mut_slice()).collect();
+        field.bulk_get_into(&pt_refs, &mut bulk_refs);
+        assert_eq!(bulk, expected);
+
+        let ys: Vec<_> = pts.iter().map(|p| p[0]).collect();
+        let xs: Vec<_> = pts.iter().map(|p| p[1]).collect();
+        let mut cols = vec![vec![f64::NAN; pts.len()]; 2];
+        let mut col_refs: Vec<&mut [f64]> = cols.iter_mut().map(|c| c.as_mut_slice()).collect();
+        field.column_get_into(&[&ys, &xs], &mut col_refs);
+        for (idx, e) in expected.iter().enumerate() {
+            assert_abs_diff_eq!(cols[0][idx], e[0], epsilon = 1e-12);
+            assert_abs_diff_eq!(cols[1][idx], e[1], epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_vector_axis_first() {
+        // a 2x2 field of 1D vectors with the vector axis first
+        let array = VecNdArray::new(vec![0u8, 10, 20, 30], RowMajor::new(&[1, 2, 2])).unwrap();
+        let field = ArrayField::try_new(
+            array,
+            VectorAxis::First,
+            Interpolation::CubicBSpline,
+            Boundary::Mirror,
+        )
+        .unwrap();
+        assert_eq!(field.extents(), &[2, 2]);
+        let mut buf = [f64::NAN];
+        field.get_into(&[1.0, 0.0], &mut buf);
+        assert_abs_diff_eq!(buf[0], 20.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_displacement() {
+        let field = ArrayField::try_new(
+            field_data(),
+            VectorAxis::Last,
+            Interpolation::Linear,
+            Boundary::Clamp,
+        )
+        .unwrap();
+        let t = Displacement::new(field);
+        let mut buf = [f64::NAN; 2];
+        t.transform_into(&[2.0, 1.0], &mut buf);
+        assert_abs_diff_eq!(buf.as_slice(), [3.0, 2.0].as_slice(), epsilon = 1e-12);
+        // by default, the field's boundary applies beyond the last element
+        t.transform_into(&[2.25, 3.25], &mut buf);
+        assert_abs_diff_eq!(buf.as_slice(), [3.25, 6.25].as_slice(), epsilon = 1e-12);
+        let t = t.with_out_of_domain(OutOfDomain::Nan);
+        t.transform_into(&[2.25, 3.25], &mut buf);
+        assert!(buf.iter().all(|b| b.is_nan()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let array = VecNdArray::new(vec![0.0f64; 3], RowMajor::new(&[3])).unwrap();
+        assert!(
+            ArrayField::try_new(
+                array,
+                VectorAxis::Last,
+                Interpolation::Linear,
+                Boundary::Clamp
+            )
+            .is_err()
+        );
+        let array = VecNdArray::new(vec![0.0f64; 4], RowMajor::new(&[2, 2, 1])).unwrap();
+        assert!(
+            ArrayField::try_new(
+                array,
+                VectorAxis::Last,
+                Interpolation::Lanczos(0),
+                Boundary::Clamp
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_edge_samples() {
+        let boundaries = [
+            Boundary::Constant(0.0),
+            Boundary::Clamp,
+            Boundary::Reflect,
+            Boundary::Mirror,
+            Boundary::Wrap,
+        ];
+        for interpolation in [Interpolation::CatmullRom, Interpolation::CubicBSpline] {
+            for boundary in boundaries {
+                let field =
+                    ArrayField::try_new(field_data(), VectorAxis::Last, interpolation, boundary);
+                if interpolation == Interpolation::CubicBSpline && boundary != Boundary::Mirror {
+                    assert!(field.is_err());
+                    continue;
+                }
+                let field = field.unwrap();
+                // both interpolate, so reproduce the samples at the corners
+                let mut buf = [f64::NAN; 2];
+                for (pt, expected) in [
+                    ([0.0, 0.0], [0.0, 0.0]),
+                    ([0.0, 3.0], [0.0, 3.0]),
+                    ([2.0, 0.0], [1.0, 0.0]),
+                    ([2.0, 3.0], [1.0, 3.0]),
+                ] {
+                    field.get_into(&pt, &mut buf);
+                    assert_abs_diff_eq!(buf.as_slice(), expected.as_slice(), epsilon = 1e-9);
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use std::{collections::BTreeMap, marker::PhantomData};
+use std::{borrow::Borrow, collections::BTreeMap, marker::PhantomData};
 
 use crate::{
     ShortVec,
-    indexer::value::{BoundedIndex, ChunkOffset, ChunkedIndex},
+    indexer::value::{BoundedIndex, ChunkOffset, ChunkSource, ChunkedIndex},
 };
 
+/// Index into a chunked array.
+///
+/// Bulk and column lookups are grouped by chunk, so each chunk is fetched once per call.
+///
+/// Where a chunk cannot be loaded, checked lookups are `None`
+/// and unchecked lookups panic with the load error;
+/// use [Self::try_get] to find out why without panicking.
 pub struct ChunkedIndexer<T, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> {
     chunked: C,
     _t: PhantomData<T>,
@@ -19,6 +26,27 @@ impl<T, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> ChunkedIndexer<T, B, C> {
             _b: Default::default(),
         }
     }
+
+    /// As [BoundedIndex::get], but fails if the coordinate's chunk could not be loaded.
+    pub fn try_get(&self, coord: &[usize]) -> Result<Option<T>, String> {
+        let Some(co) = self.chunked.get_chunk_offset(coord) else {
+            return Ok(None);
+        };
+        let Some(chunk) = self.chunked.try_get_chunk(&co.chunk_id)? else {
+            return Ok(None);
+        };
+        let chunk: &B = chunk.borrow();
+        Ok(chunk.get(&co.offset_idx))
+    }
+
+    /// Panics if the chunk is outside the array or could not be loaded.
+    fn expect_chunk(&self, chunk_id: &[usize]) -> C::Chunk<'_> {
+        match self.chunked.try_get_chunk(chunk_id) {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => panic!("Chunk {chunk_id:?} is outside the array"),
+            Err(e) => panic!("{e}"),
+        }
+    }
 }
 
 type CoordToOpt<'a, T> = BTreeMap<ShortVec<usize>, ShortVec<&'a mut Option<T>>>;
@@ -28,8 +56,9 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
 {
     fn get(&self, coord: &[usize]) -> Option<T> {
         let co = self.chunked.get_chunk_offset(coord)?;
-        let c = self.chunked.get_chunk(&co.chunk_id)?;
-        c.get(&co.offset_idx)
+        let chunk = self.chunked.get_chunk(&co.chunk_id)?;
+        let chunk: &B = chunk.borrow();
+        chunk.get(&co.offset_idx)
     }
 
     fn bulk_get_into(&self, coords: &[&[usize]], buf: &mut [Option<T>]) {
@@ -58,6 +87,7 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
                 }
                 continue;
             };
+            let chunk: &B = chunk.borrow();
             {
                 let mut coord_refs = vec![];
                 for c in coord_to_b.keys() {
@@ -94,7 +124,8 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
             // would be nice to promote this allocation out of the loop,
             // but it contains refs and we can't prove to the compiler
             // that they are cleared within the loop
-            let chunk = self.chunked.get_chunk(&chunk_id).unwrap();
+            let chunk = self.expect_chunk(&chunk_id);
+            let chunk: &B = chunk.borrow();
             {
                 let mut coord_refs = vec![];
                 for c in coord_to_b.keys() {
@@ -146,6 +177,7 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
                 }
                 continue;
             };
+            let chunk: &B = chunk.borrow();
             for coord in coord_to_b.keys() {
                 for (col, c) in inner_coords.iter_mut().zip(coord.iter()) {
                     col.push(*c);
@@ -162,6 +194,7 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
                 }
             }
             inner_b.clear();
+            inner_coords.iter_mut().for_each(|col| col.clear());
         }
     }
 
@@ -192,7 +225,8 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
             // would be nice to promote this allocation out of the loop,
             // but it contains refs and we can't prove to the compiler
             // that they are cleared within the loop
-            let chunk = self.chunked.get_chunk(&chunk_id).unwrap();
+            let chunk = self.expect_chunk(&chunk_id);
+            let chunk: &B = chunk.borrow();
             for coord in coord_to_b.keys() {
                 for (col, c) in inner_coords.iter_mut().zip(coord.iter()) {
                     col.push(*c);
@@ -209,13 +243,15 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
                 }
             }
             inner_b.clear();
+            inner_coords.iter_mut().for_each(|col| col.clear());
         }
     }
 
     fn get_unchecked(&self, coord: &[usize]) -> T {
         let co = self.chunked.get_chunk_offset(coord).unwrap();
-        let c = self.chunked.get_chunk(&co.chunk_id).unwrap();
-        c.get_unchecked(&co.offset_idx)
+        let chunk = self.expect_chunk(&co.chunk_id);
+        let chunk: &B = chunk.borrow();
+        chunk.get_unchecked(&co.offset_idx)
     }
 
     fn extents(&self) -> &[usize] {
@@ -223,39 +259,80 @@ impl<T: Clone, B: BoundedIndex<T>, C: ChunkedIndex<T, B>> BoundedIndex<T>
     }
 }
 
-#[allow(unused)]
+/// A regular grid of chunks over an array.
+///
+/// Chunks at the upper edge of each dimension are truncated to the array's extents.
 #[derive(Debug, Clone)]
 pub struct RegularChunker {
+    extents: Vec<usize>,
     chunk_shape: Vec<usize>,
     n_chunks: Vec<usize>,
 }
 
-#[allow(unused)]
 impl RegularChunker {
-    pub fn new(chunk_shape: &[usize], n_chunks: &[usize]) -> Result<Self, String> {
-        if chunk_shape.len() != n_chunks.len() {
+    pub fn try_new(extents: &[usize], chunk_shape: &[usize]) -> Result<Self, String> {
+        if chunk_shape.len() != extents.len() {
             return Err("Inconsistent dimension".into());
         }
+        if chunk_shape.contains(&0) {
+            return Err("Chunk shape must be non-zero".into());
+        }
+        let n_chunks = extents
+            .iter()
+            .zip(chunk_shape.iter())
+            .map(|(e, cs)| e.div_ceil(*cs))
+            .collect();
         Ok(Self {
+            extents: extents.to_vec(),
             chunk_shape: chunk_shape.to_vec(),
-            n_chunks: n_chunks.to_vec(),
+            n_chunks,
         })
     }
 
+    pub fn extents(&self) -> &[usize] {
+        &self.extents
+    }
+
+    pub fn chunk_shape(&self) -> &[usize] {
+        &self.chunk_shape
+    }
+
+    /// Number of chunks in each dimension.
+    pub fn n_chunks(&self) -> &[usize] {
+        &self.n_chunks
+    }
+
+    /// The extents of a chunk, which are smaller than the chunk shape at the upper edges of the array.
+    pub fn chunk_extents(&self, chunk_id: &[usize]) -> Option<ShortVec<usize>> {
+        if chunk_id.len() != self.n_chunks.len() {
+            return None;
+        }
+        chunk_id
+            .iter()
+            .zip(self.n_chunks.iter())
+            .zip(self.chunk_shape.iter().zip(self.extents.iter()))
+            .map(|((ci, nc), (cs, e))| (ci < nc).then(|| (*cs).min(e - ci * cs)))
+            .collect()
+    }
+
+    /// `None` if the coordinate is outside the array.
     pub fn get_chunk_idx(&self, coord: &[usize]) -> Option<ChunkOffset> {
+        if coord.len() != self.extents.len() {
+            return None;
+        }
         let mut chunk_id: ShortVec<usize> = smallvec::smallvec![usize::MAX; coord.len()];
         let mut offset_idx: ShortVec<usize> = smallvec::smallvec![usize::MAX; coord.len()];
-        for ((((c, cs), nc), ci), oi) in coord
+        for ((((c, cs), e), ci), oi) in coord
             .iter()
             .zip(self.chunk_shape.iter())
-            .zip(self.n_chunks.iter())
+            .zip(self.extents.iter())
             .zip(chunk_id.iter_mut())
             .zip(offset_idx.iter_mut())
         {
-            *ci = c / cs;
-            if *ci >= *nc {
+            if c >= e {
                 return None;
             }
+            *ci = c / cs;
             *oi = c % cs;
         }
         Some(ChunkOffset {
@@ -264,3 +341,230 @@ impl RegularChunker {
         })
     }
 }
+
+/// A [ChunkedIndex] over a [RegularChunker], whose chunks are loaded from a [ChunkSource] when they are needed.
+///
+/// Chunks are not retained between lookups;
+/// a source can cache them by returning a shared chunk type.
+pub struct LazyChunks<T, B: BoundedIndex<T>, S: ChunkSource<T, B>> {
+    chunker: RegularChunker,
+    source: S,
+    _t: PhantomData<T>,
+    _b: PhantomData<B>,
+}
+
+impl<T, B: BoundedIndex<T>, S: ChunkSource<T, B>> LazyChunks<T, B, S> {
+    pub fn new(chunker: RegularChunker, source: S) -> Self {
+        Self {
+            chunker,
+            source,
+            _t: Default::default(),
+            _b: Default::default(),
+        }
+    }
+
+    pub fn chunker(&self) -> &RegularChunker {
+        &self.chunker
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+}
+
+impl<T, B: BoundedIndex<T>, S: ChunkSource<T, B>> ChunkedIndex<T, B> for LazyChunks<T, B, S> {
+    type Chunk<'a>
+        = S::Chunk
+    where
+        Self: 'a;
+
+    fn get_chunk_offset(&self, coord: &[usize]) -> Option<ChunkOffset> {
+        self.chunker.get_chunk_idx(coord)
+    }
+
+    fn get_chunk(&self, chunk_id: &[usize]) -> Option<Self::Chunk<'_>> {
+        self.try_get_chunk(chunk_id).ok().flatten()
+    }
+
+    fn extents(&self) -> &[usize] {
+        self.chunker.extents()
+    }
+
+    fn try_get_chunk(&self, chunk_id: &[usize]) -> Result<Option<Self::Chunk<'_>>, String> {
+        if chunk_id.len() != self.chunker.n_chunks.len()
+            || chunk_id
+                .iter()
+                .zip(self.chunker.n_chunks.iter())
+                .any(|(ci, nc)| ci >= nc)
+        {
+            return Ok(None);
+        }
+        self.source.load_chunk(chunk_id).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const EXTENTS: [usize; 2] = [5, 7];
+
+    /// Part of a 5x7 array whose values are their flat index in the array.
+    struct Chunk {
+        origin: ShortVec<usize>,
+        extents: ShortVec<usize>,
+    }
+
+    impl BoundedIndex<usize> for Chunk {
+        fn get(&self, coord: &[usize]) -> Option<usize> {
+            (coord[0] < self.extents[0] && coord[1] < self.extents[1])
+                .then(|| self.get_unchecked(coord))
+        }
+
+        fn get_unchecked(&self, coord: &[usize]) -> usize {
+            (self.origin[0] + coord[0]) * EXTENTS[1] + self.origin[1] + coord[1]
+        }
+
+        fn extents(&self) -> &[usize] {
+            &self.extents
+        }
+    }
+
+    struct Source {
+        chunker: RegularChunker,
+        loads: AtomicUsize,
+        /// A chunk which fails to load.
+        broken: Option<[usize; 2]>,
+    }
+
+    impl ChunkSource<usize, Chunk> for Source {
+        type Chunk = Chunk;
+
+        fn load_chunk(&self, chunk_id: &[usize]) -> Result<Chunk, String> {
+            self.loads.fetch_add(1, Ordering::Relaxed);
+            if self.broken.is_some_and(|b| b == chunk_id) {
+                return Err(format!("Could not read chunk {chunk_id:?}"));
+            }
+            Ok(Chunk {
+                origin: chunk_id
+                    .iter()
+                    .zip(self.chunker.chunk_shape())
+                    .map(|(ci, cs)| ci * cs)
+                    .collect(),
+                extents: self
+                    .chunker
+                    .chunk_extents(chunk_id)
+                    .ok_or("Chunk out of range")?,
+            })
+        }
+    }
+
+    fn make_indexer() -> ChunkedIndexer<usize, Chunk, LazyChunks<usize, Chunk, Source>> {
+        make_broken_indexer(None)
+    }
+
+    fn make_broken_indexer(
+        broken: Option<[usize; 2]>,
+    ) -> ChunkedIndexer<usize, Chunk, LazyChunks<usize, Chunk, Source>> {
+        let chunker = RegularChunker::try_new(&EXTENTS, &[2, 3]).unwrap();
+        let source = Source {
+            chunker: chunker.clone(),
+            loads: AtomicUsize::new(0),
+            broken,
+        };
+        ChunkedIndexer::new(LazyChunks::new(chunker, source))
+    }
+
+    #[test]
+    fn test_edge_chunks() {
+        let chunker = RegularChunker::try_new(&EXTENTS, &[2, 3]).unwrap();
+        assert_eq!(chunker.n_chunks(), &[3, 3]);
+        assert_eq!(chunker.chunk_extents(&[0, 0]).unwrap().as_slice(), &[2, 3]);
+        assert_eq!(chunker.chunk_extents(&[2, 2]).unwrap().as_slice(), &[1, 1]);
+        assert!(chunker.chunk_extents(&[3, 0]).is_none());
+
+        let co = chunker.get_chunk_idx(&[4, 6]).unwrap();
+        assert_eq!(co.chunk_id.as_slice(), &[2, 2]);
+        assert_eq!(co.offset_idx.as_slice(), &[0, 0]);
+        // inside the last chunks' full shape, but outside the array
+        assert!(chunker.get_chunk_idx(&[5, 0]).is_none());
+        assert!(chunker.get_chunk_idx(&[0, 7]).is_none());
+
+        assert!(RegularChunker::try_new(&EXTENTS, &[2]).is_err());
+        assert!(RegularChunker::try_new(&EXTENTS, &[2, 0]).is_err());
+    }
+
+    #[test]
+    fn test_lazy_chunks() {
+        let indexer = make_indexer();
+        assert_eq!(indexer.extents(), &EXTENTS);
+        for row in 0..EXTENTS[0] {
+            for col in 0..EXTENTS[1] {
+                assert_eq!(indexer.get(&[row, col]), Some(row * EXTENTS[1] + col));
+            }
+        }
+        assert_eq!(indexer.get(&[5, 0]), None);
+        assert_eq!(indexer.get(&[0, 7]), None);
+    }
+
+    #[test]
+    fn test_bulk_loads_each_chunk_once() {
+        let indexer = make_indexer();
+        let coords = [[0, 0], [1, 2], [4, 6], [0, 1], [5, 5]];
+        let coord_refs: Vec<&[usize]> = coords.iter().map(|c| c.as_slice()).collect();
+        let mut buf = vec![None; coords.len()];
+        indexer.bulk_get_into(&coord_refs, &mut buf);
+        assert_eq!(buf, [Some(0), Some(9), Some(34), Some(1), None]);
+        assert_eq!(indexer.chunked.source().loads.load(Ordering::Relaxed), 2);
+
+        let columns = [vec![0, 1, 4, 0, 5], vec![0, 2, 6, 1, 5]];
+        let column_refs: Vec<&[usize]> = columns.iter().map(|c| c.as_slice()).collect();
+        let mut buf = vec![None; coords.len()];
+        indexer.column_get_into(&column_refs, &mut buf);
+        assert_eq!(buf, [Some(0), Some(9), Some(34), Some(1), None]);
+        assert_eq!(indexer.chunked.source().loads.load(Ordering::Relaxed), 4);
+    }
+
+    fn assert_load_panics(f: impl FnOnce()) {
+        let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_err();
+        let msg = err.downcast_ref::<String>().unwrap();
+        assert_eq!(msg, "Could not read chunk [0, 1]");
+    }
+
+    #[test]
+    fn test_load_failure() {
+        // chunk [0, 1] holds rows 0-1, columns 3-5
+        let indexer = make_broken_indexer(Some([0, 1]));
+        assert_eq!(indexer.get(&[0, 0]), Some(0));
+        assert_eq!(indexer.get(&[1, 4]), None);
+        assert_eq!(indexer.try_get(&[0, 0]), Ok(Some(0)));
+        assert_eq!(indexer.try_get(&[5, 0]), Ok(None));
+        assert!(indexer.try_get(&[1, 4]).is_err());
+        assert_eq!(indexer.get_unchecked(&[0, 0]), 0);
+        assert_load_panics(|| {
+            indexer.get_unchecked(&[1, 4]);
+        });
+
+        let coords = [[0, 0], [1, 4], [0, 3]];
+        let coord_refs: Vec<&[usize]> = coords.iter().map(|c| c.as_slice()).collect();
+        let mut buf = vec![None; coords.len()];
+        indexer.bulk_get_into(&coord_refs, &mut buf);
+        assert_eq!(buf, [Some(0), None, None]);
+        assert_load_panics(|| {
+            let mut buf = vec![99; coords.len()];
+            indexer.bulk_get_into_unchecked(&coord_refs, &mut buf);
+        });
+
+        let columns = [vec![0, 1, 0], vec![0, 4, 3]];
+        let column_refs: Vec<&[usize]> = columns.iter().map(|c| c.as_slice()).collect();
+        let mut buf = vec![None; coords.len()];
+        indexer.column_get_into(&column_refs, &mut buf);
+        assert_eq!(buf, [Some(0), None, None]);
+        assert_load_panics(|| {
+            let mut buf = vec![99; coords.len()];
+            indexer.column_get_into_unchecked(&column_refs, &mut buf);
+        });
+    }
+}
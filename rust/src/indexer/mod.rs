@@ -9,7 +9,9 @@ mod idx_ndarray;
 #[cfg(feature = "ndarray")]
 pub use idx_ndarray::{ArrayRefWrapper, ArrayViewWrapper, ArrayWrapper};
 mod idx_chunked;
-pub use idx_chunked::ChunkedIndexer;
+pub use idx_chunked::{ChunkedIndexer, LazyChunks, RegularChunker};
+mod field;
+pub use field::{ArrayField, Boundary, Interpolation, VectorAxis};
 pub mod value;
 
 #[derive(Debug, Clone)]
@@ -1,8 +1,9 @@
-use std::marker::PhantomData;
-
 use crate::{
     Transformation,
-    indexer::{Ravelled, value::RealIndex},
+    indexer::{
+        Ravelled,
+        value::{RealIndex, bulk_get_present, column_get_present},
+    },
     transforms::Affine,
 };
 
@@ -12,14 +13,12 @@ pub struct Sampler<T, I: RealIndex<T>> {
     columns: bool,
     indexer: I,
     grid_shape: Vec<usize>,
-    _t: PhantomData<T>,
+    missing: T,
 }
 
-impl<T, I: RealIndex<T>> Sampler<T, I> {
-    fn n_coords(&self) -> usize {
-        self.grid_shape.iter().product()
-    }
-
+impl<T: Default, I: RealIndex<T>> Sampler<T, I> {
+    /// Coordinates containing NaN are not sampled, and take `T::default()`;
+    /// see [Sampler::set_missing].
     pub fn try_new(indexer: I, grid_shape: &[usize], columns: bool) -> Result<Self, String> {
         if grid_shape.len() != indexer.ndim() {
             return Err("Incompatible grid dimensionality".into());
@@ -38,9 +37,24 @@ impl<T, I: RealIndex<T>> Sampler<T, I> {
             columns,
             indexer,
             grid_shape: grid_shape.to_vec(),
-            _t: Default::default(),
+            missing: Default::default(),
         })
     }
+}
+
+impl<T, I: RealIndex<T>> Sampler<T, I> {
+    fn n_coords(&self) -> usize {
+        self.grid_shape.iter().product()
+    }
+
+    /// Set the value for coordinates containing NaN.
+    pub fn set_missing(&mut self, missing: T) {
+        self.missing = missing;
+    }
+
+    pub fn missing(&self) -> &T {
+        &self.missing
+    }
 
     /// Affine columns should be orthogonal, but this is not checked.
     pub fn set_orientation(&mut self, affine: Affine) {
@@ -53,31 +67,24 @@ impl<T, I: RealIndex<T>> Sampler<T, I> {
         }
     }
 
-    pub fn get_into(&self, buf: &mut [T]) {
-        let coords: Vec<_> = self.coord_buffer.chunks().collect();
-        if self.columns {
-            self.indexer.column_get_into(&coords, buf);
-        } else {
-            self.indexer.bulk_get_into(&coords, buf);
-        }
-    }
-
     pub fn grid_shape(&self) -> &[usize] {
         &self.grid_shape
     }
 }
 
-impl<T: Default, I: RealIndex<T>> Sampler<T, I> {
-    pub fn get(&self) -> Vec<T> {
-        let mut buf: Vec<_> = std::iter::repeat_with(Default::default)
-            .take(self.n_coords())
-            .collect();
+impl<T: Clone, I: RealIndex<T>> Sampler<T, I> {
+    pub fn get_into(&self, buf: &mut [T]) {
         let coords: Vec<_> = self.coord_buffer.chunks().collect();
         if self.columns {
-            self.indexer.column_get_into(&coords, &mut buf);
+            column_get_present(&self.indexer, &coords, buf, &self.missing);
         } else {
-            self.indexer.bulk_get_into(&coords, &mut buf);
+            bulk_get_present(&self.indexer, &coords, buf, &self.missing);
         }
+    }
+
+    pub fn get(&self) -> Vec<T> {
+        let mut buf = vec![self.missing.clone(); self.n_coords()];
+        self.get_into(&mut buf);
         buf
     }
 }
@@ -148,7 +155,7 @@ mod tests {
         init_logger();
         let extents = vec![3, 2];
         let ravel = row_base_coords(&extents);
-        let expected = vec![
+        let expected = [
             [0.0, 0.0],
             [0.0, 1.0],
             [1.0, 0.0],
@@ -166,7 +173,7 @@ mod tests {
         init_logger();
         let extents = vec![3, 2];
         let ravel = column_base_coords(&extents);
-        let expected = vec![
+        let expected = [
             [0.0, 0.0, 1.0, 1.0, 2.0, 2.0],
             [0.0, 1.0, 0.0, 1.0, 0.0, 1.0],
         ];
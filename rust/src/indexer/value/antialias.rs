@@ -0,0 +1,335 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{
+    Transformation,
+    indexer::{
+        Ravelled,
+        value::{Numeric, RealIndex, UnboundedIndex},
+    },
+};
+
+/// Number of points whose footprints are fetched together in bulk and column lookups.
+const BATCH_SIZE: usize = 256;
+
+/// Footprints narrower than this standard deviation (in source pixels) are linearly interpolated instead.
+const MIN_SIGMA: f64 = 0.25;
+
+/// Number of standard deviations beyond which the Gaussian is truncated.
+const TRUNCATE: f64 = 3.0;
+
+/// Footprints wider than this standard deviation (in source pixels) are clamped to it,
+/// bounding each point to at most 25 neighbours per axis.
+/// Downsampling by more than about 9x per axis is therefore only partially anti-aliased;
+/// resample from a coarser level of a pyramid instead.
+const MAX_SIGMA: f64 = 4.0;
+
+/// Resample through a transformation, averaging over each output sample's footprint in the source
+/// to avoid aliasing when downsampling.
+///
+/// The footprint is estimated from the transformation's [Jacobian](Transformation::jacobian_into)
+/// at each sample: an output step of 1 covers a distance `s` along each source axis,
+/// which is integrated over with a Gaussian of standard deviation `(s - 1) / 2` (as in scikit-image),
+/// clamped to at most 4 source pixels.
+/// Where the transformation does not shrink an axis, that axis is linearly interpolated.
+///
+/// Samples which transform to a non-finite position are NaN (0 for integers).
+///
+/// This replaces a [Transformed](super::Transformed) wrapping an interpolator:
+/// the transformation maps from the sampled (output) space into the indexed (source) space.
+pub struct AntiAliased<T, U: UnboundedIndex<T>> {
+    unbounded: U,
+    transform: Arc<dyn Transformation>,
+    _t: PhantomData<T>,
+}
+
+impl<T, U: UnboundedIndex<T>> AntiAliased<T, U> {
+    pub fn try_new(unbounded: U, transform: Arc<dyn Transformation>) -> Result<Self, String> {
+        if transform.output_ndim() != unbounded.ndim() {
+            return Err("Dimensionality mismatch".into());
+        }
+        Ok(Self {
+            unbounded,
+            transform,
+            _t: Default::default(),
+        })
+    }
+}
+
+/// The source neighbours of a batch of points, with their weights.
+#[derive(Default)]
+struct Footprints {
+    /// Source coordinate of each neighbour, ravelled by neighbour.
+    coords: Vec<isize>,
+    weights: Vec<f64>,
+    /// Number of neighbours of each point.
+    counts: Vec<usize>,
+    // per-point scratch space
+    starts: Vec<isize>,
+    lens: Vec<usize>,
+    axis_weights: Vec<f64>,
+    digits: Vec<usize>,
+}
+
+impl Footprints {
+    fn clear(&mut self) {
+        self.coords.clear();
+        self.weights.clear();
+        self.counts.clear();
+    }
+
+    /// Add the neighbours of a point at `pos` in the source, with the given Jacobian (source rows, sample columns).
+    /// A point at a non-finite position has no neighbours.
+    fn push(&mut self, pos: &[f64], jacobian: &[f64]) {
+        let ndim = pos.len();
+        let in_ndim = jacobian.len() / ndim;
+        self.starts.clear();
+        self.lens.clear();
+        self.axis_weights.clear();
+        if pos.iter().any(|p| !p.is_finite()) {
+            self.counts.push(0);
+            return;
+        }
+
+        for (p, row) in pos.iter().zip(jacobian.chunks_exact(in_ndim)) {
+            let scale = row.iter().map(|j| j * j).sum::<f64>().sqrt();
+            let sigma = ((scale - 1.0) / 2.0).min(MAX_SIGMA);
+            let len_before = self.axis_weights.len();
+            if sigma.is_nan() || sigma < MIN_SIGMA {
+                let floor = p.floor();
+                let t = p - floor;
+                self.starts.push(floor as isize);
+                self.axis_weights.extend([1.0 - t, t]);
+            } else {
+                let radius = TRUNCATE * sigma;
+                let first = (p - radius).ceil();
+                // bounded by the radius, even where coordinates are too large to step through
+                let n_taps = ((p + radius).floor() - first).clamp(0.0, 2.0 * radius) as usize + 1;
+                self.starts.push(first as isize);
+                for i in 0..n_taps {
+                    let z = (first + i as f64 - p) / sigma;
+                    self.axis_weights.push((-0.5 * z * z).exp());
+                }
+                let axis = &mut self.axis_weights[len_before..];
+                let total: f64 = axis.iter().sum();
+                axis.iter_mut().for_each(|w| *w /= total);
+            }
+            self.lens.push(self.axis_weights.len() - len_before);
+        }
+
+        let count: usize = self.lens.iter().product();
+        self.counts.push(count);
+        self.digits.clear();
+        self.digits.resize(ndim, 0);
+        for _ in 0..count {
+            let mut weight = 1.0;
+            let mut offset = 0;
+            for (dim, digit) in self.digits.iter().enumerate() {
+                self.coords
+                    .push(self.starts[dim].saturating_add(*digit as isize));
+                weight *= self.axis_weights[offset + digit];
+                offset += self.lens[dim];
+            }
+            self.weights.push(weight);
+            // advance the mixed-radix counter, first axis fastest
+            for (digit, len) in self.digits.iter_mut().zip(self.lens.iter()) {
+                *digit += 1;
+                if *digit < *len {
+                    break;
+                }
+                *digit = 0;
+            }
+        }
+    }
+
+    fn reduce_into<T: Numeric, V: Numeric>(&self, values: &[V], buf: &mut [T]) {
+        let mut start = 0;
+        for (b, count) in buf.iter_mut().zip(self.counts.iter()) {
+            if *count == 0 {
+                *b = T::from_f64(f64::NAN);
+                continue;
+            }
+            let stop = start + count;
+            let total = self.weights[start..stop]
+                .iter()
+                .zip(&values[start..stop])
+                .map(|(w, v)| w * v.to_f64())
+                .sum();
+            *b = T::from_f64(total);
+            start = stop;
+        }
+    }
+}
+
+impl<T: Numeric, U: UnboundedIndex<T>> RealIndex<T> for AntiAliased<T, U> {
+    fn get(&self, coord: &[f64]) -> T {
+        let mut buf = [T::default()];
+        self.bulk_get_into(&[coord], &mut buf);
+        buf[0]
+    }
+
+    fn bulk_get_into(&self, coords: &[&[f64]], buf: &mut [T]) {
+        let out_ndim = self.transform.output_ndim();
+        let mut jacobian = vec![f64::NAN; out_ndim * self.transform.input_ndim()];
+        let mut footprints = Footprints::default();
+        for (coord_batch, buf_batch) in coords.chunks(BATCH_SIZE).zip(buf.chunks_mut(BATCH_SIZE)) {
+            let mut positions = Ravelled::new_full(out_ndim, coord_batch.len(), f64::NAN);
+            self.transform
+                .bulk_transform_into(coord_batch, &mut positions.chunks_mut().collect::<Vec<_>>());
+
+            footprints.clear();
+            for (coord, pos) in coord_batch.iter().zip(positions.chunks()) {
+                self.transform.jacobian_into(coord, &mut jacobian);
+                footprints.push(pos, &jacobian);
+            }
+
+            let neighbours = Ravelled::new_data(out_ndim, std::mem::take(&mut footprints.coords))
+                .expect("one coordinate per axis");
+            let mut values = vec![T::default(); footprints.weights.len()];
+            self.unbounded
+                .bulk_get_into(&neighbours.chunks().collect::<Vec<_>>(), &mut values);
+            footprints.reduce_into(&values, buf_batch);
+        }
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
+        let in_ndim = self.transform.input_ndim();
+        let out_ndim = self.transform.output_ndim();
+        let n_coords = columns[0].len();
+        let mut jacobian = vec![f64::NAN; out_ndim * in_ndim];
+        let mut coord = vec![f64::NAN; in_ndim];
+        let mut pos = vec![f64::NAN; out_ndim];
+        let mut footprints = Footprints::default();
+        for (start, buf_batch) in (0..n_coords)
+            .step_by(BATCH_SIZE)
+            .zip(buf.chunks_mut(BATCH_SIZE))
+        {
+            let stop = start + buf_batch.len();
+            let col_batch: Vec<&[f64]> = columns.iter().map(|c| &c[start..stop]).collect();
+            let mut positions = vec![vec![f64::NAN; stop - start]; out_ndim];
+            self.transform.column_transform_into(
+                &col_batch,
+                &mut positions
+                    .iter_mut()
+                    .map(|p| p.as_mut_slice())
+                    .collect::<Vec<_>>(),
+            );
+
+            footprints.clear();
+            for idx in 0..stop - start {
+                coord
+                    .iter_mut()
+                    .zip(&col_batch)
+                    .for_each(|(c, col)| *c = col[idx]);
+                pos.iter_mut()
+                    .zip(&positions)
+                    .for_each(|(p, col)| *p = col[idx]);
+                self.transform.jacobian_into(&coord, &mut jacobian);
+                footprints.push(&pos, &jacobian);
+            }
+
+            let n_values = footprints.weights.len();
+            let mut neighbours = vec![Vec::with_capacity(n_values); out_ndim];
+            for c in footprints.coords.chunks_exact(out_ndim) {
+                neighbours
+                    .iter_mut()
+                    .zip(c)
+                    .for_each(|(col, val)| col.push(*val));
+            }
+            let mut values = vec![T::default(); n_values];
+            let refs: Vec<&[isize]> = neighbours.iter().map(|c| c.as_ref()).collect();
+            self.unbounded.column_get_into(&refs, &mut values);
+            footprints.reduce_into(&values, buf_batch);
+        }
+    }
+
+    fn ndim(&self) -> usize {
+        self.transform.input_ndim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+    use crate::transforms::{Identity, Scale};
+
+    /// Alternating stripes along the first axis, which alias badly when downsampled.
+    struct Stripes;
+
+    impl UnboundedIndex<f64> for Stripes {
+        fn get(&self, coord: &[isize]) -> f64 {
+            (coord[0].rem_euclid(2) * 100) as f64
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_dimensionality() {
+        assert!(AntiAliased::<f64, _>::try_new(Stripes, Arc::new(Identity::new(3))).is_err());
+    }
+
+    #[test]
+    fn test_identity_interpolates() {
+        let aa = AntiAliased::try_new(Stripes, Arc::new(Identity::new(2))).unwrap();
+        assert_abs_diff_eq!(aa.get(&[1.0, 0.0]), 100.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(aa.get(&[2.25, 7.0]), 25.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_downsampling_averages() {
+        // every output sample lands on an even (0-valued) stripe
+        let aa =
+            AntiAliased::try_new(Stripes, Arc::new(Scale::try_new(&[8.0, 1.0]).unwrap())).unwrap();
+        let coords: Vec<[f64; 2]> = (0..5).map(|i| [i as f64, 3.0]).collect();
+        let coord_refs: Vec<&[f64]> = coords.iter().map(|c| c.as_slice()).collect();
+        let mut buf = vec![0.0; coords.len()];
+        aa.bulk_get_into(&coord_refs, &mut buf);
+        for b in buf.iter() {
+            assert_abs_diff_eq!(*b, 50.0, epsilon = 1.0);
+        }
+
+        let xs: Vec<_> = coords.iter().map(|c| c[0]).collect();
+        let ys: Vec<_> = coords.iter().map(|c| c[1]).collect();
+        let mut col_buf = vec![0.0; coords.len()];
+        aa.column_get_into(&[&xs, &ys], &mut col_buf);
+        for (a, b) in col_buf.iter().zip(buf.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_footprint_bounded() {
+        let mut fp = Footprints::default();
+        // extreme downsampling is clamped to the maximum footprint
+        fp.push(&[10.0, 0.0], &[1e6, 0.0, 0.0, 1e6]);
+        assert_eq!(fp.counts, vec![25 * 25]);
+        // coordinates too large to step through by 1
+        fp.push(&[1e300, -1e300], &[1e6, 0.0, 0.0, 1e6]);
+        assert!(fp.counts[1] <= 26 * 26);
+        fp.push(&[f64::INFINITY, 0.0], &[1e6, 0.0, 0.0, 1e6]);
+        fp.push(&[f64::NAN, 0.0], &[1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(&fp.counts[2..], &[0, 0]);
+    }
+
+    #[test]
+    fn test_non_finite() {
+        let aa =
+            AntiAliased::try_new(Stripes, Arc::new(Scale::try_new(&[8.0, 1.0]).unwrap())).unwrap();
+        let coords: [&[f64]; 4] = [
+            &[f64::NAN, 0.0],
+            &[f64::INFINITY, 0.0],
+            &[1e300, 0.0],
+            &[1.0, 0.0],
+        ];
+        let mut buf = [0.0; 4];
+        aa.bulk_get_into(&coords, &mut buf);
+        assert!(buf[0].is_nan());
+        assert!(buf[1].is_nan());
+        assert!(buf[2].is_finite());
+        assert_abs_diff_eq!(buf[3], 50.0, epsilon = 1.0);
+    }
+}
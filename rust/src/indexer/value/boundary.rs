@@ -0,0 +1,196 @@
+use std::marker::PhantomData;
+
+use crate::{
+    ShortVec,
+    indexer::{
+        Ravelled,
+        value::{BoundedIndex, UnboundedIndex},
+    },
+};
+
+/// Repeat the edge voxel: `a a a | a b c d | d d d`.
+fn clamp(c: isize, extent: isize) -> usize {
+    c.clamp(0, extent - 1) as usize
+}
+
+/// Reflect about the edge of the array, repeating the edge voxel: `c b a | a b c d | d c b`.
+fn reflect(c: isize, extent: isize) -> usize {
+    let period = 2 * extent;
+    let c = c.rem_euclid(period);
+    (if c >= extent { period - 1 - c } else { c }) as usize
+}
+
+/// Reflect about the edge voxel, without repeating it: `d c b | a b c d | c b a`.
+fn mirror(c: isize, extent: isize) -> usize {
+    if extent == 1 {
+        return 0;
+    }
+    let period = 2 * (extent - 1);
+    let c = c.rem_euclid(period);
+    (if c >= extent { period - c } else { c }) as usize
+}
+
+/// Tile the array periodically: `b c d | a b c d | a b c`.
+fn wrap(c: isize, extent: isize) -> usize {
+    c.rem_euclid(extent) as usize
+}
+
+/// Define an [UnboundedIndex] adapter which maps every coordinate into the array with the given function.
+macro_rules! boundary_adapter {
+    ($(#[$meta:meta])* $name:ident, $fold:ident) => {
+        $(#[$meta])*
+        pub struct $name<T, A: BoundedIndex<T>> {
+            bounded: A,
+            extents: Vec<isize>,
+            _t: PhantomData<T>,
+        }
+
+        impl<T, A: BoundedIndex<T>> $name<T, A> {
+            /// Fails if the array is empty along any axis.
+            pub fn try_new(bounded: A) -> Result<Self, String> {
+                if bounded.extents().contains(&0) {
+                    return Err("Array has no voxels to extend".into());
+                }
+                let extents = bounded.extents().iter().map(|u| *u as isize).collect();
+                Ok(Self {
+                    bounded,
+                    extents,
+                    _t: Default::default(),
+                })
+            }
+
+            fn fold_into(&self, coord: impl IntoIterator<Item = isize>, buf: &mut [usize]) {
+                for ((c, ext), b) in coord.into_iter().zip(&self.extents).zip(buf.iter_mut()) {
+                    *b = $fold(c, *ext);
+                }
+            }
+        }
+
+        impl<T, A: BoundedIndex<T>> UnboundedIndex<T> for $name<T, A> {
+            fn get(&self, coord: &[isize]) -> T {
+                let mut new_coord: ShortVec<usize> = ShortVec::from_elem(0, coord.len());
+                self.fold_into(coord.iter().copied(), &mut new_coord);
+                self.bounded.get_unchecked(&new_coord)
+            }
+
+            fn bulk_get_into(&self, coords: &[&[isize]], buf: &mut [T]) {
+                let mut new_coords = Ravelled::new_full(self.extents.len(), coords.len(), 0);
+                for (coord, new_coord) in coords.iter().zip(new_coords.chunks_mut()) {
+                    self.fold_into(coord.iter().copied(), new_coord);
+                }
+                self.bounded
+                    .bulk_get_into_unchecked(&new_coords.chunks().collect::<Vec<_>>(), buf);
+            }
+
+            fn column_get_into(&self, columns: &[&[isize]], buf: &mut [T]) {
+                let new_cols: Vec<Vec<usize>> = columns
+                    .iter()
+                    .zip(&self.extents)
+                    .map(|(col, ext)| col.iter().map(|c| $fold(*c, *ext)).collect())
+                    .collect();
+                let col_refs: Vec<&[usize]> = new_cols.iter().map(|c| c.as_ref()).collect();
+                self.bounded.column_get_into_unchecked(&col_refs, buf);
+            }
+
+            fn ndim(&self) -> usize {
+                self.bounded.ndim()
+            }
+        }
+    };
+}
+
+boundary_adapter!(
+    /// Extends an array by repeating the edge voxel: `a a a | a b c d | d d d`.
+    Clamp,
+    clamp
+);
+boundary_adapter!(
+    /// Extends an array by reflecting it about its edge, repeating the edge voxel: `c b a | a b c d | d c b`.
+    ///
+    /// Equivalent to scipy.ndimage's `reflect` mode.
+    Reflect,
+    reflect
+);
+boundary_adapter!(
+    /// Extends an array by reflecting it about the edge voxel, without repeating it: `d c b | a b c d | c b a`.
+    ///
+    /// Equivalent to scipy.ndimage's `mirror` mode.
+    Mirror,
+    mirror
+);
+boundary_adapter!(
+    /// Extends an array by tiling it periodically: `b c d | a b c d | a b c`.
+    Wrap,
+    wrap
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: isize = 4;
+    const COORDS: [isize; 12] = [-6, -5, -4, -3, -2, -1, 0, 3, 4, 5, 6, 7];
+
+    fn folded(f: fn(isize, isize) -> usize) -> Vec<usize> {
+        COORDS.iter().map(|c| f(*c, EXTENT)).collect()
+    }
+
+    #[test]
+    fn test_clamp() {
+        assert_eq!(folded(clamp), vec![0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn test_reflect() {
+        assert_eq!(folded(reflect), vec![2, 3, 3, 2, 1, 0, 0, 3, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_mirror() {
+        assert_eq!(folded(mirror), vec![0, 1, 2, 3, 2, 1, 0, 3, 2, 1, 0, 1]);
+        assert_eq!(mirror(-3, 1), 0);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(folded(wrap), vec![2, 3, 0, 1, 2, 3, 0, 3, 0, 1, 2, 3]);
+    }
+
+    struct Row(Vec<u8>, [usize; 1]);
+
+    impl BoundedIndex<u8> for Row {
+        fn get(&self, coord: &[usize]) -> Option<u8> {
+            self.0.get(coord[0]).copied()
+        }
+
+        fn get_unchecked(&self, coord: &[usize]) -> u8 {
+            self.0[coord[0]]
+        }
+
+        fn extents(&self) -> &[usize] {
+            &self.1
+        }
+    }
+
+    #[test]
+    fn test_adapter_paths_agree() {
+        let wrapped = Wrap::try_new(Row(vec![10, 20, 30], [3])).unwrap();
+        let coords: Vec<[isize; 1]> = COORDS.iter().map(|c| [*c]).collect();
+        let expected: Vec<u8> = coords.iter().map(|c| wrapped.get(c)).collect();
+
+        let mut buf = vec![0; coords.len()];
+        let coord_refs: Vec<&[isize]> = coords.iter().map(|c| c.as_slice()).collect();
+        wrapped.bulk_get_into(&coord_refs, &mut buf);
+        assert_eq!(buf, expected);
+
+        let mut buf = vec![0; coords.len()];
+        wrapped.column_get_into(&[&COORDS], &mut buf);
+        assert_eq!(buf, expected);
+        assert_eq!(&expected[..3], &[10, 20, 30]);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(Clamp::try_new(Row(vec![], [0])).is_err());
+    }
+}
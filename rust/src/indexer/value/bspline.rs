@@ -0,0 +1,305 @@
+use std::marker::PhantomData;
+
+use crate::indexer::value::{
+    BoundedIndex, Numeric, RealIndex, UnboundedIndex,
+    kernel::{self, CubicBSplineKernel},
+};
+
+/// Pole of the cubic B-spline prefilter.
+const POLE: f64 = -0.267_949_192_431_122_7; // sqrt(3) - 2
+
+/// Relative precision at which the prefilter's causal initialisation is truncated.
+const TOLERANCE: f64 = 1e-12;
+
+/// Cubic B-spline coefficients of an array of samples.
+///
+/// The prefilter treats the samples as mirrored about the edges of the array
+/// (`d c b | a b c d | c b a`), as scipy's `spline_filter` does.
+#[derive(Debug, Clone)]
+pub struct SplineCoefficients {
+    data: Vec<f64>,
+    extents: Vec<usize>,
+    strides: Vec<usize>,
+}
+
+impl SplineCoefficients {
+    /// Read every sample of the array and compute its spline coefficients.
+    pub fn from_bounded<T: Numeric, B: BoundedIndex<T>>(bounded: &B) -> Self {
+        let extents = bounded.extents().to_vec();
+        let mut strides = vec![1; extents.len()];
+        for dim in (0..extents.len().saturating_sub(1)).rev() {
+            strides[dim] = strides[dim + 1] * extents[dim + 1];
+        }
+        let len = extents.iter().product();
+
+        let mut data = Vec::with_capacity(len);
+        let mut coord = vec![0; extents.len()];
+        for _ in 0..len {
+            data.push(bounded.get_unchecked(&coord).to_f64());
+            for (c, max) in coord.iter_mut().zip(extents.iter()).rev() {
+                *c += 1;
+                if *c < *max {
+                    break;
+                }
+                *c = 0;
+            }
+        }
+
+        let mut coefs = Self {
+            data,
+            extents,
+            strides,
+        };
+        for dim in 0..coefs.extents.len() {
+            coefs.filter_axis(dim);
+        }
+        coefs
+    }
+
+    fn filter_axis(&mut self, dim: usize) {
+        let len = self.extents[dim];
+        let stride = self.strides[dim];
+        if len < 2 {
+            return;
+        }
+        let mut line = vec![0.0; len];
+        let n_outer = self.data.len() / (len * stride);
+        for outer in 0..n_outer {
+            for inner in 0..stride {
+                let offset = outer * len * stride + inner;
+                for (idx, l) in line.iter_mut().enumerate() {
+                    *l = self.data[offset + idx * stride];
+                }
+                filter_line(&mut line);
+                for (idx, l) in line.iter().enumerate() {
+                    self.data[offset + idx * stride] = *l;
+                }
+            }
+        }
+    }
+}
+
+/// Convert samples into cubic B-spline coefficients in place, with mirrored boundaries.
+///
+/// See Unser et al. (1993), "B-spline signal processing".
+fn filter_line(line: &mut [f64]) {
+    let len = line.len();
+    let z = POLE;
+    let gain = (1.0 - z) * (1.0 - 1.0 / z);
+    line.iter_mut().for_each(|c| *c *= gain);
+
+    // causal initialisation
+    let horizon = (TOLERANCE.ln() / z.abs().ln()).ceil() as usize;
+    line[0] = if horizon < len {
+        let mut zn = z;
+        let mut sum = line[0];
+        for c in &line[1..horizon] {
+            sum += zn * c;
+            zn *= z;
+        }
+        sum
+    } else {
+        let iz = 1.0 / z;
+        let mut zn = z;
+        let mut z2n = z.powi(len as i32 - 1);
+        let mut sum = line[0] + z2n * line[len - 1];
+        z2n = z2n * z2n * iz;
+        for c in &line[1..len - 1] {
+            sum += (zn + z2n) * c;
+            zn *= z;
+            z2n *= iz;
+        }
+        sum / (1.0 - zn * zn)
+    };
+
+    for idx in 1..len {
+        line[idx] += z * line[idx - 1];
+    }
+
+    // anticausal initialisation
+    line[len - 1] = (z / (z * z - 1.0)) * (z * line[len - 2] + line[len - 1]);
+
+    for idx in (0..len - 1).rev() {
+        line[idx] = z * (line[idx + 1] - line[idx]);
+    }
+}
+
+impl BoundedIndex<f64> for SplineCoefficients {
+    fn get(&self, coord: &[usize]) -> Option<f64> {
+        if coord.len() != self.extents.len() || coord.iter().zip(&self.extents).any(|(c, e)| c >= e)
+        {
+            return None;
+        }
+        Some(self.get_unchecked(coord))
+    }
+
+    fn get_unchecked(&self, coord: &[usize]) -> f64 {
+        let idx: usize = coord.iter().zip(&self.strides).map(|(c, s)| c * s).sum();
+        self.data[idx]
+    }
+
+    fn extents(&self) -> &[usize] {
+        &self.extents
+    }
+}
+
+/// Cubic B-spline interpolation of prefiltered [SplineCoefficients].
+///
+/// The coefficients are wrapped in a boundary layer (e.g. [Const](super::Const)) to make them unbounded;
+/// this determines the values of the spline within 2 pixels of the edge of the array and beyond.
+/// With [Mirror](super::Mirror), results match scipy's `map_coordinates(order=3, mode="mirror")`:
+/// `BSpline::new(Mirror::try_new(SplineCoefficients::from_bounded(&arr))?)`.
+pub struct BSpline<T, U: UnboundedIndex<f64>> {
+    coefficients: U,
+    _t: PhantomData<T>,
+}
+
+impl<T, U: UnboundedIndex<f64>> BSpline<T, U> {
+    pub fn new(coefficients: U) -> Self {
+        Self {
+            coefficients,
+            _t: Default::default(),
+        }
+    }
+}
+
+impl<T, U: UnboundedIndex<f64>> From<U> for BSpline<T, U> {
+    fn from(value: U) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Numeric, U: UnboundedIndex<f64>> RealIndex<T> for BSpline<T, U> {
+    fn get(&self, coord: &[f64]) -> T {
+        let mut buf = [T::default()];
+        self.bulk_get_into(&[coord], &mut buf);
+        buf[0]
+    }
+
+    fn bulk_get_into(&self, coords: &[&[f64]], buf: &mut [T]) {
+        kernel::bulk_get_into(&CubicBSplineKernel, &self.coefficients, coords, buf);
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
+        kernel::column_get_into(&CubicBSplineKernel, &self.coefficients, columns, buf);
+    }
+
+    fn ndim(&self) -> usize {
+        self.coefficients.ndim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+    use crate::indexer::value::Mirror;
+
+    struct Samples {
+        data: Vec<u8>,
+        extents: Vec<usize>,
+    }
+
+    impl BoundedIndex<u8> for Samples {
+        fn get(&self, coord: &[usize]) -> Option<u8> {
+            (coord[0] < self.extents[0] && coord[1] < self.extents[1])
+                .then(|| self.get_unchecked(coord))
+        }
+
+        fn get_unchecked(&self, coord: &[usize]) -> u8 {
+            self.data[coord[0] * self.extents[1] + coord[1]]
+        }
+
+        fn extents(&self) -> &[usize] {
+            &self.extents
+        }
+    }
+
+    fn samples() -> Samples {
+        Samples {
+            data: vec![0, 10, 200, 30, 5, 50, 60, 70, 255, 90, 1, 2, 3, 4, 100],
+            extents: vec![3, 5],
+        }
+    }
+
+    #[test]
+    fn test_interpolates_samples() {
+        let samples = samples();
+        let spline: BSpline<f64, _> =
+            BSpline::new(Mirror::try_new(SplineCoefficients::from_bounded(&samples)).unwrap());
+        for row in 0..3 {
+            for col in 0..5 {
+                let expected = samples.get_unchecked(&[row, col]) as f64;
+                let actual = spline.get(&[row as f64, col as f64]);
+                assert_abs_diff_eq!(actual, expected, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirror_reference() {
+        // as scipy.ndimage.map_coordinates(samples, coords.T, order=3, mode="mirror"):
+        // the mirrored interpolating spline, solved and evaluated in exact rational arithmetic
+        let expected = [
+            ([0.5, 0.5], 21.108_398_437_5),
+            ([1.25, 2.75], 189.598_251_342_773_44),
+            ([0.1, 3.9], 6.236_781_294_642_857),
+            ([2.0, 1.5], 5.734_375),
+            ([1.75, 0.2], 9.016_897_321_428_571),
+            // beyond the edges
+            ([2.4, 4.0], 103.32),
+            ([-0.3, 4.6], 39.089_833_571_428_57),
+        ];
+        let spline: BSpline<f64, _> =
+            BSpline::new(Mirror::try_new(SplineCoefficients::from_bounded(&samples())).unwrap());
+        for (coord, value) in expected {
+            assert_abs_diff_eq!(spline.get(&coord), value, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_filter_line_short() {
+        // every mirrored-periodic signal of length 2 is reproduced
+        let mut line = vec![1.0, 4.0];
+        filter_line(&mut line);
+        let c = |i: isize| line[i.rem_euclid(2) as usize];
+        let at = |i: isize| (c(i - 1) + 4.0 * c(i) + c(i + 1)) / 6.0;
+        assert_abs_diff_eq!(at(0), 1.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(at(1), 4.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_filter_line_long() {
+        // long enough for the truncated causal initialisation
+        let samples: Vec<f64> = (0..40).map(|i| ((i * 37) % 11) as f64).collect();
+        let mut line = samples.clone();
+        filter_line(&mut line);
+        let c = |i: isize| {
+            line[if i < 0 {
+                -i
+            } else if i > 39 {
+                78 - i
+            } else {
+                i
+            } as usize]
+        };
+        for (i, s) in samples.iter().enumerate() {
+            let i = i as isize;
+            let at = (c(i - 1) + 4.0 * c(i) + c(i + 1)) / 6.0;
+            assert_abs_diff_eq!(at, s, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_constant_between_samples() {
+        let data = Samples {
+            data: vec![7; 15],
+            extents: vec![3, 5],
+        };
+        let spline: BSpline<u8, _> =
+            BSpline::new(Mirror::try_new(SplineCoefficients::from_bounded(&data)).unwrap());
+        assert_eq!(spline.get(&[0.3, 3.7]), 7);
+        assert_eq!(spline.get(&[1.5, 0.5]), 7);
+    }
+}
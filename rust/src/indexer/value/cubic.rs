@@ -0,0 +1,93 @@
+use std::marker::PhantomData;
+
+use crate::indexer::value::{
+    Numeric, RealIndex, UnboundedIndex,
+    kernel::{self, CatmullRomKernel},
+};
+
+/// Catmull-Rom cubic convolution between the 4^N integer neighbours of each coordinate.
+///
+/// Passes through the sample values, and reproduces polynomials up to quadratic.
+/// As the kernel has negative lobes, interpolated values may overshoot the range of the samples;
+/// integer pixel types saturate.
+pub struct CatmullRom<T, U: UnboundedIndex<T>> {
+    unbounded: U,
+    _t: PhantomData<T>,
+}
+
+impl<T, U: UnboundedIndex<T>> CatmullRom<T, U> {
+    pub fn new(unbounded: U) -> Self {
+        Self {
+            unbounded,
+            _t: Default::default(),
+        }
+    }
+}
+
+impl<T, U: UnboundedIndex<T>> From<U> for CatmullRom<T, U> {
+    fn from(value: U) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Numeric, U: UnboundedIndex<T>> RealIndex<T> for CatmullRom<T, U> {
+    fn get(&self, coord: &[f64]) -> T {
+        let mut buf = [T::default()];
+        self.bulk_get_into(&[coord], &mut buf);
+        buf[0]
+    }
+
+    fn bulk_get_into(&self, coords: &[&[f64]], buf: &mut [T]) {
+        kernel::bulk_get_into(&CatmullRomKernel, &self.unbounded, coords, buf);
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
+        kernel::column_get_into(&CatmullRomKernel, &self.unbounded, columns, buf);
+    }
+
+    fn ndim(&self) -> usize {
+        self.unbounded.ndim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+
+    /// f(x, y) = x^2 - 3xy + 2y + 5
+    struct Quadratic;
+
+    impl UnboundedIndex<f64> for Quadratic {
+        fn get(&self, coord: &[isize]) -> f64 {
+            expected(coord[0] as f64, coord[1] as f64)
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    fn expected(x: f64, y: f64) -> f64 {
+        x * x - 3.0 * x * y + 2.0 * y + 5.0
+    }
+
+    const POINTS: [[f64; 2]; 4] = [[0.0, 0.0], [0.5, 0.25], [-1.75, 2.5], [3.0, -0.1]];
+
+    #[test]
+    fn test_reproduces_quadratic() {
+        let cr = CatmullRom::new(Quadratic);
+        for [x, y] in POINTS {
+            assert_abs_diff_eq!(cr.get(&[x, y]), expected(x, y), epsilon = 1e-9);
+        }
+
+        let xs: Vec<_> = POINTS.iter().map(|p| p[0]).collect();
+        let ys: Vec<_> = POINTS.iter().map(|p| p[1]).collect();
+        let mut buf = vec![0.0; xs.len()];
+        cr.column_get_into(&[&xs, &ys], &mut buf);
+        for (b, [x, y]) in buf.into_iter().zip(POINTS) {
+            assert_abs_diff_eq!(b, expected(x, y), epsilon = 1e-9);
+        }
+    }
+}
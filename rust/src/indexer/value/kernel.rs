@@ -0,0 +1,540 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use crate::indexer::{
+    Ravelled,
+    value::{Numeric, UnboundedIndex},
+};
+
+/// Number of points whose neighbours are fetched together in bulk and column lookups.
+const BATCH_SIZE: usize = 1024;
+
+/// Maximum number of distinct offsets whose weights are cached for each axis.
+const MAX_CACHED: usize = 4096;
+
+/// A separable interpolation kernel, applied independently along each axis.
+pub(crate) trait Kernel {
+    /// The number of neighbours which contribute along each axis.
+    fn support(&self) -> usize;
+
+    /// The first neighbour of a coordinate, relative to the coordinate's floor.
+    fn first(&self) -> isize;
+
+    /// Write the weights of the neighbours of a coordinate along one axis into `buf` (of length `support`),
+    /// where `t` is the coordinate's offset from its floor.
+    fn weights_into(&self, t: f64, buf: &mut [f64]);
+
+    /// Whether weights are expensive enough to be worth caching along each axis.
+    fn cached(&self) -> bool {
+        false
+    }
+}
+
+/// Weights `1 - t` and `t` of the neighbours either side.
+pub(crate) struct LinearKernel;
+
+impl Kernel for LinearKernel {
+    fn support(&self) -> usize {
+        2
+    }
+
+    fn first(&self) -> isize {
+        0
+    }
+
+    fn weights_into(&self, t: f64, buf: &mut [f64]) {
+        buf[0] = 1.0 - t;
+        buf[1] = t;
+    }
+}
+
+/// Keys' cubic convolution kernel with `a = -0.5`.
+pub(crate) struct CatmullRomKernel;
+
+impl Kernel for CatmullRomKernel {
+    fn support(&self) -> usize {
+        4
+    }
+
+    fn first(&self) -> isize {
+        -1
+    }
+
+    fn weights_into(&self, t: f64, buf: &mut [f64]) {
+        let t2 = t * t;
+        let t3 = t2 * t;
+        buf[0] = 0.5 * (-t3 + 2.0 * t2 - t);
+        buf[1] = 0.5 * (3.0 * t3 - 5.0 * t2 + 2.0);
+        buf[2] = 0.5 * (-3.0 * t3 + 4.0 * t2 + t);
+        buf[3] = 0.5 * (t3 - t2);
+    }
+}
+
+/// The cubic B-spline basis function.
+pub(crate) struct CubicBSplineKernel;
+
+impl Kernel for CubicBSplineKernel {
+    fn support(&self) -> usize {
+        4
+    }
+
+    fn first(&self) -> isize {
+        -1
+    }
+
+    fn weights_into(&self, t: f64, buf: &mut [f64]) {
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let u = 1.0 - t;
+        buf[0] = u * u * u / 6.0;
+        buf[1] = (4.0 - 6.0 * t2 + 3.0 * t3) / 6.0;
+        buf[2] = (1.0 + 3.0 * t + 3.0 * t2 - 3.0 * t3) / 6.0;
+        buf[3] = t3 / 6.0;
+    }
+}
+
+/// Sinc windowed by a wider sinc, `sinc(x) sinc(x / a)` for `|x| < a`.
+///
+/// Weights are normalised to sum to 1, so that flat regions stay flat.
+pub(crate) struct LanczosKernel {
+    pub radius: usize,
+}
+
+impl LanczosKernel {
+    fn eval(&self, x: f64) -> f64 {
+        let a = self.radius as f64;
+        if x == 0.0 {
+            1.0
+        } else if x.abs() >= a {
+            0.0
+        } else {
+            let px = PI * x;
+            a * px.sin() * (px / a).sin() / (px * px)
+        }
+    }
+}
+
+impl Kernel for LanczosKernel {
+    fn support(&self) -> usize {
+        2 * self.radius
+    }
+
+    fn first(&self) -> isize {
+        1 - self.radius as isize
+    }
+
+    fn weights_into(&self, t: f64, buf: &mut [f64]) {
+        let first = self.first();
+        for (idx, b) in buf.iter_mut().enumerate() {
+            *b = self.eval(t - (first + idx as isize) as f64);
+        }
+        let total: f64 = buf.iter().sum();
+        buf.iter_mut().for_each(|b| *b /= total);
+    }
+
+    fn cached(&self) -> bool {
+        true
+    }
+}
+
+/// Weights along one axis, by the offset from the floor which they were computed for.
+#[derive(Default)]
+struct WeightCache {
+    index: HashMap<u64, usize>,
+    table: Vec<f64>,
+}
+
+impl WeightCache {
+    fn weights_into<K: Kernel>(&mut self, kernel: &K, t: f64, buf: &mut [f64]) {
+        let support = buf.len();
+        if let Some(idx) = self.index.get(&t.to_bits()) {
+            buf.copy_from_slice(&self.table[idx * support..(idx + 1) * support]);
+            return;
+        }
+        kernel.weights_into(t, buf);
+        if self.index.len() < MAX_CACHED {
+            self.index.insert(t.to_bits(), self.index.len());
+            self.table.extend_from_slice(buf);
+        }
+    }
+}
+
+/// The neighbours of points under a kernel in some dimensionality.
+struct Plan<'k, K: Kernel> {
+    kernel: &'k K,
+    ndim: usize,
+    support: usize,
+    /// Offset of each neighbour from the first, ravelled by neighbour.
+    offsets: Vec<usize>,
+    n_neighbours: usize,
+    /// One per axis, if the kernel is cached.
+    caches: Vec<WeightCache>,
+}
+
+impl<'k, K: Kernel> Plan<'k, K> {
+    fn new(kernel: &'k K, ndim: usize) -> Self {
+        let support = kernel.support();
+        let n_neighbours = support.pow(ndim as u32);
+        let mut offsets = Vec::with_capacity(n_neighbours * ndim);
+        for neighbour in 0..n_neighbours {
+            let mut rem = neighbour;
+            for _ in 0..ndim {
+                offsets.push(rem % support);
+                rem /= support;
+            }
+        }
+        let caches = if kernel.cached() {
+            std::iter::repeat_with(WeightCache::default)
+                .take(ndim)
+                .collect()
+        } else {
+            vec![]
+        };
+        Self {
+            kernel,
+            ndim,
+            support,
+            offsets,
+            n_neighbours,
+            caches,
+        }
+    }
+
+    /// Push the first neighbour and the weights along each axis for a point,
+    /// returning whether it was pushed.
+    ///
+    /// Points with a non-finite coordinate are not pushed, so that their neighbours are not looked up.
+    fn push_point(
+        &mut self,
+        coord: impl IntoIterator<Item = f64>,
+        starts: &mut Vec<isize>,
+        weights: &mut Vec<f64>,
+    ) -> bool {
+        let (n_starts, n_weights) = (starts.len(), weights.len());
+        for (dim, c) in coord.into_iter().enumerate() {
+            if !c.is_finite() {
+                starts.truncate(n_starts);
+                weights.truncate(n_weights);
+                return false;
+            }
+            let floor = c.floor();
+            let t = c - floor;
+            // huge coordinates saturate, and are resolved by the boundary
+            starts.push((floor as isize).saturating_add(self.kernel.first()));
+            let len = weights.len();
+            weights.resize(len + self.support, 0.0);
+            match self.caches.get_mut(dim) {
+                Some(cache) => cache.weights_into(self.kernel, t, &mut weights[len..]),
+                None => self.kernel.weights_into(t, &mut weights[len..]),
+            }
+        }
+        true
+    }
+
+    /// Call `f` with each axis and coordinate of each neighbour of each point.
+    fn neighbours(&self, starts: &[isize], mut f: impl FnMut(usize, isize)) {
+        for point_starts in starts.chunks_exact(self.ndim) {
+            for offsets in self.offsets.chunks_exact(self.ndim) {
+                for (dim, (s, o)) in point_starts.iter().zip(offsets).enumerate() {
+                    f(dim, s.saturating_add(*o as isize));
+                }
+            }
+        }
+    }
+
+    /// Reduce the neighbours' values for each point,
+    /// where each neighbour's weight is the product of its weights along each axis.
+    ///
+    /// Points which were not pushed are [missing](Reduce::missing).
+    fn reduce_into<V, T, R: Reduce<V, T>>(
+        &self,
+        reducer: &R,
+        pushed: &[bool],
+        weights: &[f64],
+        values: &[V],
+        buf: &mut [T],
+    ) {
+        let mut neighbour_weights = vec![0.0; self.n_neighbours];
+        let mut point_weights = weights.chunks_exact(self.ndim * self.support);
+        let mut point_values = values.chunks_exact(self.n_neighbours);
+        for (b, p) in buf.iter_mut().zip(pushed) {
+            if !*p {
+                *b = reducer.missing();
+                continue;
+            }
+            let point_weights = point_weights.next().expect("weights for each pushed point");
+            let point_values = point_values.next().expect("values for each pushed point");
+            for (w, offsets) in neighbour_weights
+                .iter_mut()
+                .zip(self.offsets.chunks_exact(self.ndim))
+            {
+                *w = offsets
+                    .iter()
+                    .enumerate()
+                    .map(|(dim, o)| point_weights[dim * self.support + o])
+                    .product();
+            }
+            *b = reducer.reduce(&neighbour_weights, point_values);
+        }
+    }
+}
+
+/// How the values of a point's neighbours are combined into one, given each neighbour's weight.
+pub(crate) trait Reduce<V, T> {
+    fn reduce(&self, weights: &[f64], values: &[V]) -> T;
+
+    /// The value of a point with a non-finite coordinate, which has no neighbours.
+    fn missing(&self) -> T;
+}
+
+/// The weighted sum of the neighbours' values.
+pub(crate) struct WeightedSum;
+
+impl<V: Numeric, T: Numeric> Reduce<V, T> for WeightedSum {
+    fn reduce(&self, weights: &[f64], values: &[V]) -> T {
+        let total = weights
+            .iter()
+            .zip(values)
+            .map(|(w, v)| w * v.to_f64())
+            .sum();
+        T::from_f64(total)
+    }
+
+    /// NaN, or 0 for integers.
+    fn missing(&self) -> T {
+        T::from_f64(f64::NAN)
+    }
+}
+
+/// Interpolate a list of points, fetching the neighbours of each batch of points together.
+pub(crate) fn bulk_get_into<K: Kernel, V: Numeric, U: UnboundedIndex<V>, T: Numeric>(
+    kernel: &K,
+    unbounded: &U,
+    coords: &[&[f64]],
+    buf: &mut [T],
+) {
+    bulk_reduce_into(kernel, &WeightedSum, unbounded, coords, buf);
+}
+
+/// Interpolate columns of coordinates, fetching the neighbours of each batch of points together.
+pub(crate) fn column_get_into<K: Kernel, V: Numeric, U: UnboundedIndex<V>, T: Numeric>(
+    kernel: &K,
+    unbounded: &U,
+    columns: &[&[f64]],
+    buf: &mut [T],
+) {
+    column_reduce_into(kernel, &WeightedSum, unbounded, columns, buf);
+}
+
+/// Combine the neighbours of each of a list of points with `reducer`,
+/// fetching the neighbours of each batch of points together.
+pub(crate) fn bulk_reduce_into<K, V, U, T, R>(
+    kernel: &K,
+    reducer: &R,
+    unbounded: &U,
+    coords: &[&[f64]],
+    buf: &mut [T],
+) where
+    K: Kernel,
+    V: Clone + Default,
+    U: UnboundedIndex<V>,
+    R: Reduce<V, T>,
+{
+    let mut plan = Plan::new(kernel, unbounded.ndim());
+    let mut starts = vec![];
+    let mut weights = vec![];
+    for (coord_batch, buf_batch) in coords.chunks(BATCH_SIZE).zip(buf.chunks_mut(BATCH_SIZE)) {
+        starts.clear();
+        weights.clear();
+        let pushed: Vec<bool> = coord_batch
+            .iter()
+            .map(|coord| plan.push_point(coord.iter().copied(), &mut starts, &mut weights))
+            .collect();
+
+        let n_values = starts.len() / plan.ndim.max(1) * plan.n_neighbours;
+        let mut values = vec![V::default(); n_values];
+        if n_values > 0 {
+            let mut flat = Vec::with_capacity(n_values * plan.ndim);
+            plan.neighbours(&starts, |_, c| flat.push(c));
+            let neighbours = Ravelled::new_data(plan.ndim, flat).expect("one coordinate per axis");
+            unbounded.bulk_get_into(&neighbours.chunks().collect::<Vec<_>>(), &mut values);
+        }
+        plan.reduce_into(reducer, &pushed, &weights, &values, buf_batch);
+    }
+}
+
+/// Combine the neighbours of each point in columns of coordinates with `reducer`,
+/// fetching the neighbours of each batch of points together.
+pub(crate) fn column_reduce_into<K, V, U, T, R>(
+    kernel: &K,
+    reducer: &R,
+    unbounded: &U,
+    columns: &[&[f64]],
+    buf: &mut [T],
+) where
+    K: Kernel,
+    V: Clone + Default,
+    U: UnboundedIndex<V>,
+    R: Reduce<V, T>,
+{
+    let mut plan = Plan::new(kernel, unbounded.ndim());
+    let n_coords = columns[0].len();
+    let mut starts = vec![];
+    let mut weights = vec![];
+    for (start, buf_batch) in (0..n_coords)
+        .step_by(BATCH_SIZE)
+        .zip(buf.chunks_mut(BATCH_SIZE))
+    {
+        starts.clear();
+        weights.clear();
+        let pushed: Vec<bool> = (start..start + buf_batch.len())
+            .map(|idx| {
+                plan.push_point(
+                    columns.iter().map(|col| col[idx]),
+                    &mut starts,
+                    &mut weights,
+                )
+            })
+            .collect();
+
+        let n_values = starts.len() / plan.ndim.max(1) * plan.n_neighbours;
+        let mut values = vec![V::default(); n_values];
+        if n_values > 0 {
+            let mut neighbours = vec![Vec::with_capacity(n_values); plan.ndim];
+            plan.neighbours(&starts, |dim, c| neighbours[dim].push(c));
+            let refs: Vec<&[isize]> = neighbours.iter().map(|c| c.as_ref()).collect();
+            unbounded.column_get_into(&refs, &mut values);
+        }
+        plan.reduce_into(reducer, &pushed, &weights, &values, buf_batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+
+    fn check_partition_of_unity(kernel: &impl Kernel) {
+        let mut buf = vec![0.0; kernel.support()];
+        for t in [0.0, 0.1, 0.25, 0.5, 0.9] {
+            kernel.weights_into(t, &mut buf);
+            assert_abs_diff_eq!(buf.iter().sum::<f64>(), 1.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_partition_of_unity() {
+        check_partition_of_unity(&LinearKernel);
+        check_partition_of_unity(&CatmullRomKernel);
+        check_partition_of_unity(&CubicBSplineKernel);
+        check_partition_of_unity(&LanczosKernel { radius: 3 });
+    }
+
+    #[test]
+    fn test_neighbour_offsets() {
+        let plan = Plan::new(&LinearKernel, 2);
+        assert_eq!(plan.n_neighbours, 4);
+        assert_eq!(plan.offsets, vec![0, 0, 1, 0, 0, 1, 1, 1]);
+    }
+
+    /// Linear weights, counting how often they are computed.
+    #[derive(Default)]
+    struct Counting(Cell<usize>);
+
+    impl Kernel for Counting {
+        fn support(&self) -> usize {
+            2
+        }
+
+        fn first(&self) -> isize {
+            0
+        }
+
+        fn weights_into(&self, t: f64, buf: &mut [f64]) {
+            self.0.set(self.0.get() + 1);
+            LinearKernel.weights_into(t, buf);
+        }
+
+        fn cached(&self) -> bool {
+            true
+        }
+    }
+
+    struct Sum;
+
+    impl UnboundedIndex<f64> for Sum {
+        fn get(&self, coord: &[isize]) -> f64 {
+            coord.iter().sum::<isize>() as f64
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_weights_cached_per_axis() {
+        // a 4x3 grid of points at half-pixel offsets, in column order
+        let xs: Vec<f64> = (0..12).map(|i| (i / 3) as f64 + 0.5).collect();
+        let ys: Vec<f64> = (0..12).map(|i| (i % 3) as f64 * 2.0 + 0.25).collect();
+        let kernel = Counting::default();
+        let mut buf = vec![0.0; 12];
+        column_get_into(&kernel, &Sum, &[&xs, &ys], &mut buf);
+        // one offset along each axis
+        assert_eq!(kernel.0.get(), 2);
+        for ((b, x), y) in buf.into_iter().zip(xs).zip(ys) {
+            assert_abs_diff_eq!(b, x + y, epsilon = 1e-12);
+        }
+    }
+
+    /// The same value everywhere, however far out.
+    struct Flat;
+
+    impl UnboundedIndex<f64> for Flat {
+        fn get(&self, _coord: &[isize]) -> f64 {
+            1.0
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    fn check_non_finite(kernel: &impl Kernel) {
+        let xs = [
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            1e300,
+            -1e300,
+            0.5,
+        ];
+        let ys = [0.0, 0.0, 0.0, 0.0, -1e300, 0.5];
+        let mut buf = [0.0f64; 6];
+        column_get_into(kernel, &Flat, &[&xs, &ys], &mut buf);
+        let coords: Vec<[f64; 2]> = xs.iter().zip(ys).map(|(x, y)| [*x, y]).collect();
+        let coord_refs: Vec<&[f64]> = coords.iter().map(|c| c.as_slice()).collect();
+        let mut bulk_buf = [0.0f64; 6];
+        bulk_get_into(kernel, &Flat, &coord_refs, &mut bulk_buf);
+        for b in [buf, bulk_buf] {
+            assert!(b[..3].iter().all(|v| v.is_nan()));
+            for v in b[3..].iter() {
+                assert_abs_diff_eq!(*v, 1.0, epsilon = 1e-12);
+            }
+        }
+
+        let mut int_buf = [7u8; 2];
+        column_get_into(kernel, &Flat, &[&xs[2..4], &ys[2..4]], &mut int_buf);
+        assert_eq!(int_buf, [0, 1]);
+    }
+
+    #[test]
+    fn test_non_finite() {
+        check_non_finite(&LinearKernel);
+        check_non_finite(&CatmullRomKernel);
+        check_non_finite(&CubicBSplineKernel);
+        check_non_finite(&LanczosKernel { radius: 3 });
+    }
+}
@@ -0,0 +1,107 @@
+use std::marker::PhantomData;
+
+use crate::indexer::value::{
+    Numeric, RealIndex, UnboundedIndex,
+    kernel::{self, LanczosKernel},
+};
+
+/// Lanczos (windowed-sinc) interpolation between the (2a)^N integer neighbours of each coordinate,
+/// where `a` is the radius.
+///
+/// Kernel weights are normalised to sum to 1, as in ImageMagick and Fiji.
+/// Weights are cached along each axis within a lookup,
+/// so sampling a grid (e.g. with [Sampler](crate::indexer::Sampler)) computes them once per row and column.
+/// As the kernel has negative lobes, interpolated values may overshoot the range of the samples;
+/// integer pixel types saturate.
+pub struct Lanczos<T, U: UnboundedIndex<T>> {
+    unbounded: U,
+    kernel: LanczosKernel,
+    _t: PhantomData<T>,
+}
+
+impl<T, U: UnboundedIndex<T>> Lanczos<T, U> {
+    /// Radius 2 or 3 are typical.
+    pub fn try_new(unbounded: U, radius: usize) -> Result<Self, String> {
+        if radius == 0 {
+            return Err("Lanczos radius must be at least 1".into());
+        }
+        Ok(Self {
+            unbounded,
+            kernel: LanczosKernel { radius },
+            _t: Default::default(),
+        })
+    }
+
+    pub fn radius(&self) -> usize {
+        self.kernel.radius
+    }
+}
+
+impl<T: Numeric, U: UnboundedIndex<T>> RealIndex<T> for Lanczos<T, U> {
+    fn get(&self, coord: &[f64]) -> T {
+        let mut buf = [T::default()];
+        self.bulk_get_into(&[coord], &mut buf);
+        buf[0]
+    }
+
+    fn bulk_get_into(&self, coords: &[&[f64]], buf: &mut [T]) {
+        kernel::bulk_get_into(&self.kernel, &self.unbounded, coords, buf);
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
+        kernel::column_get_into(&self.kernel, &self.unbounded, columns, buf);
+    }
+
+    fn ndim(&self) -> usize {
+        self.unbounded.ndim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+
+    /// An impulse at the origin.
+    struct Impulse;
+
+    impl UnboundedIndex<f64> for Impulse {
+        fn get(&self, coord: &[isize]) -> f64 {
+            if coord.iter().all(|c| *c == 0) {
+                1.0
+            } else {
+                0.0
+            }
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_zero_radius() {
+        assert!(Lanczos::<f64, _>::try_new(Impulse, 0).is_err());
+    }
+
+    #[test]
+    fn test_impulse_response() {
+        let lanczos = Lanczos::try_new(Impulse, 3).unwrap();
+        assert_abs_diff_eq!(lanczos.get(&[0.0, 0.0]), 1.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(lanczos.get(&[1.0, 0.0]), 0.0, epsilon = 1e-12);
+        // negative lobe between the first and second neighbours
+        assert!(lanczos.get(&[1.5, 0.0]) < 0.0);
+        // separable and symmetric
+        let a = lanczos.get(&[0.3, -0.7]);
+        let b = lanczos.get(&[-0.7, 0.3]);
+        assert_abs_diff_eq!(a, b, epsilon = 1e-12);
+
+        let xs = [0.3, 1.5];
+        let ys = [-0.7, 0.0];
+        let mut buf = [0.0; 2];
+        lanczos.column_get_into(&[&xs, &ys], &mut buf);
+        assert_abs_diff_eq!(buf[0], a, epsilon = 1e-12);
+        assert_abs_diff_eq!(buf[1], lanczos.get(&[1.5, 0.0]), epsilon = 1e-12);
+    }
+}
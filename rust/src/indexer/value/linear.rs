@@ -0,0 +1,153 @@
+use std::marker::PhantomData;
+
+use crate::indexer::value::{
+    Numeric, RealIndex, UnboundedIndex,
+    kernel::{self, LinearKernel},
+};
+
+/// N-linear interpolation (bilinear in 2D, trilinear in 3D) between the
+/// 2^N integer neighbours of each coordinate.
+///
+/// As with the other interpolators, coordinates which are infinite or NaN are not looked up,
+/// and give NaN (0 for integer pixel types).
+pub struct Linear<T, U: UnboundedIndex<T>> {
+    unbounded: U,
+    _t: PhantomData<T>,
+}
+
+impl<T, U: UnboundedIndex<T>> Linear<T, U> {
+    pub fn new(unbounded: U) -> Self {
+        Self {
+            unbounded,
+            _t: Default::default(),
+        }
+    }
+}
+
+impl<T, U: UnboundedIndex<T>> From<U> for Linear<T, U> {
+    fn from(value: U) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Numeric, U: UnboundedIndex<T>> RealIndex<T> for Linear<T, U> {
+    fn get(&self, coord: &[f64]) -> T {
+        let mut buf = [T::default()];
+        self.bulk_get_into(&[coord], &mut buf);
+        buf[0]
+    }
+
+    fn bulk_get_into(&self, coords: &[&[f64]], buf: &mut [T]) {
+        kernel::bulk_get_into(&LinearKernel, &self.unbounded, coords, buf);
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
+        kernel::column_get_into(&LinearKernel, &self.unbounded, columns, buf);
+    }
+
+    fn ndim(&self) -> usize {
+        self.unbounded.ndim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+
+    /// f(x, y) = 1 + 2x + 3y + xy, which N-linear interpolation reproduces exactly.
+    #[derive(Default)]
+    struct Bilinear {
+        calls: Cell<usize>,
+    }
+
+    impl UnboundedIndex<f64> for Bilinear {
+        fn get(&self, coord: &[isize]) -> f64 {
+            self.calls.set(self.calls.get() + 1);
+            let (x, y) = (coord[0] as f64, coord[1] as f64);
+            1.0 + 2.0 * x + 3.0 * y + x * y
+        }
+
+        fn bulk_get_into(&self, coords: &[&[isize]], buf: &mut [f64]) {
+            let calls = self.calls.get();
+            for (c, b) in coords.iter().zip(buf.iter_mut()) {
+                *b = self.get(c);
+            }
+            self.calls.set(calls + 1);
+        }
+
+        fn column_get_into(&self, columns: &[&[isize]], buf: &mut [f64]) {
+            let calls = self.calls.get();
+            for (idx, b) in buf.iter_mut().enumerate() {
+                *b = self.get(&[columns[0][idx], columns[1][idx]]);
+            }
+            self.calls.set(calls + 1);
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    fn expected(x: f64, y: f64) -> f64 {
+        1.0 + 2.0 * x + 3.0 * y + x * y
+    }
+
+    const POINTS: [[f64; 2]; 4] = [[0.0, 0.0], [0.5, 0.25], [-1.75, 2.5], [3.0, -0.1]];
+
+    #[test]
+    fn test_get() {
+        let lin = Linear::new(Bilinear::default());
+        for [x, y] in POINTS {
+            assert_abs_diff_eq!(lin.get(&[x, y]), expected(x, y), epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_bulk_batched() {
+        let lin = Linear::new(Bilinear::default());
+        let coords: Vec<&[f64]> = POINTS.iter().map(|p| p.as_slice()).collect();
+        let mut buf = vec![0.0; coords.len()];
+        lin.bulk_get_into(&coords, &mut buf);
+        for (b, [x, y]) in buf.into_iter().zip(POINTS) {
+            assert_abs_diff_eq!(b, expected(x, y), epsilon = 1e-12);
+        }
+        assert_eq!(lin.unbounded.calls.get(), 1);
+    }
+
+    #[test]
+    fn test_column_batched() {
+        let lin = Linear::new(Bilinear::default());
+        let xs: Vec<_> = POINTS.iter().map(|p| p[0]).collect();
+        let ys: Vec<_> = POINTS.iter().map(|p| p[1]).collect();
+        let mut buf = vec![0.0; xs.len()];
+        lin.column_get_into(&[&xs, &ys], &mut buf);
+        for (b, [x, y]) in buf.into_iter().zip(POINTS) {
+            assert_abs_diff_eq!(b, expected(x, y), epsilon = 1e-12);
+        }
+        assert_eq!(lin.unbounded.calls.get(), 1);
+    }
+
+    struct Steps;
+
+    impl UnboundedIndex<u8> for Steps {
+        fn get(&self, coord: &[isize]) -> u8 {
+            (coord[0] * 100).clamp(0, 255) as u8
+        }
+
+        fn ndim(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn test_integer_pixels() {
+        let lin = Linear::new(Steps);
+        assert_eq!(lin.get(&[0.25]), 25);
+        assert_eq!(lin.get(&[1.5]), 150);
+        assert_eq!(lin.get(&[2.8]), 244);
+    }
+}
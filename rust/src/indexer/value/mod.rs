@@ -1,17 +1,59 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{borrow::Borrow, marker::PhantomData, sync::Arc};
 
 use crate::{ShortVec, Transformation, indexer::Ravelled};
 use smallvec::smallvec;
 
+mod antialias;
+pub use antialias::AntiAliased;
+mod boundary;
+pub use boundary::{Clamp, Mirror, Reflect, Wrap};
+mod bspline;
+pub use bspline::{BSpline, SplineCoefficients};
+mod cubic;
+pub use cubic::CatmullRom;
+mod kernel;
+mod lanczos;
+pub use lanczos::Lanczos;
+mod linear;
+pub use linear::Linear;
+mod mode;
+pub use mode::Mode;
+mod numeric;
+pub use numeric::Numeric;
+
 pub struct ChunkOffset {
     pub chunk_id: ShortVec<usize>,
     pub offset_idx: ShortVec<usize>,
 }
 
 pub trait ChunkedIndex<T, B: BoundedIndex<T>> {
+    /// A chunk, which may be borrowed from the index, shared (e.g. `Arc<B>`), or owned.
+    type Chunk<'a>: Borrow<B>
+    where
+        Self: 'a;
+
     fn get_chunk_offset(&self, coord: &[usize]) -> Option<ChunkOffset>;
-    fn get_chunk(&self, chunk_id: &[usize]) -> Option<&B>;
+    fn get_chunk(&self, chunk_id: &[usize]) -> Option<Self::Chunk<'_>>;
     fn extents(&self) -> &[usize];
+
+    /// As [Self::get_chunk], but fails if the chunk exists and could not be loaded.
+    ///
+    /// By default, chunks can always be loaded.
+    fn try_get_chunk(&self, chunk_id: &[usize]) -> Result<Option<Self::Chunk<'_>>, String> {
+        Ok(self.get_chunk(chunk_id))
+    }
+}
+
+/// Loads chunks on demand, e.g. from storage.
+///
+/// Chunks at the upper edge of an array may be smaller than the others,
+/// or padded to the full chunk shape; padding is never read.
+pub trait ChunkSource<T, B: BoundedIndex<T>> {
+    /// An owned or shared (e.g. `Arc<B>`, for a source which caches) chunk.
+    type Chunk: Borrow<B>;
+
+    /// Fails if the chunk could not be loaded, e.g. due to an I/O error.
+    fn load_chunk(&self, chunk_id: &[usize]) -> Result<Self::Chunk, String>;
 }
 
 pub trait BoundedIndex<T> {
@@ -89,7 +131,7 @@ pub trait RealIndex<T> {
             for (dim_idx, col) in columns.iter().enumerate() {
                 coord[dim_idx] = col[idx];
             }
-            buf[0] = self.get(&coord);
+            buf[idx] = self.get(&coord);
         }
     }
 
@@ -129,11 +171,7 @@ fn unbound_to_bound_iter<'a>(
     extents: impl IntoIterator<Item = &'a isize>,
     buf: &mut [usize],
 ) -> bool {
-    for ((c, max), b) in coord
-        .into_iter()
-        .zip(extents.into_iter())
-        .zip(buf.iter_mut())
-    {
+    for ((c, max), b) in coord.into_iter().zip(extents).zip(buf.iter_mut()) {
         let Some(c2) = unbound_to_bound_elem(c, max) else {
             return false;
         };
@@ -175,7 +213,7 @@ impl<T: Copy + Default, A: BoundedIndex<T>> UnboundedIndex<T> for Const<T, A> {
             let mut out_buf = vec![Default::default(); new_coords.len()];
             self.bounded
                 .bulk_get_into_unchecked(&new_coord_refs, &mut out_buf);
-            for (idx, val) in indices.into_iter().zip(out_buf.into_iter()) {
+            for (idx, val) in indices.into_iter().zip(out_buf) {
                 buf[idx] = val;
             }
         }
@@ -213,7 +251,7 @@ impl<T: Copy + Default, A: BoundedIndex<T>> UnboundedIndex<T> for Const<T, A> {
             .into_iter()
             .zip(buf.iter_mut())
             .filter_map(|(s, b)| (!s).then_some(b))
-            .zip(inner_buf.into_iter())
+            .zip(inner_buf)
         {
             *b = res;
         }
@@ -273,13 +311,89 @@ impl<T, U: UnboundedIndex<T>> RealIndex<T> for NearestNeighbour<T, U> {
     }
 }
 
+/// Whether a coordinate is missing, i.e. has any NaN component.
+fn is_missing(coord: &[f64]) -> bool {
+    coord.iter().any(|c| c.is_nan())
+}
+
+/// Look up the coordinates which are not [missing](is_missing), filling the others with `missing`.
+pub(crate) fn bulk_get_present<T: Clone, R: RealIndex<T> + ?Sized>(
+    indexer: &R,
+    coords: &[&[f64]],
+    buf: &mut [T],
+    missing: &T,
+) {
+    if !coords.iter().any(|c| is_missing(c)) {
+        indexer.bulk_get_into(coords, buf);
+        return;
+    }
+    let present: Vec<&[f64]> = coords.iter().copied().filter(|c| !is_missing(c)).collect();
+    let mut values = vec![missing.clone(); present.len()];
+    if !present.is_empty() {
+        indexer.bulk_get_into(&present, &mut values);
+    }
+    let mut values = values.into_iter();
+    for (coord, b) in coords.iter().zip(buf.iter_mut()) {
+        *b = if is_missing(coord) {
+            missing.clone()
+        } else {
+            values.next().expect("one value per present coordinate")
+        };
+    }
+}
+
+/// Look up the columnar coordinates which are not [missing](is_missing), filling the others with `missing`.
+pub(crate) fn column_get_present<T: Clone, R: RealIndex<T> + ?Sized>(
+    indexer: &R,
+    columns: &[&[f64]],
+    buf: &mut [T],
+    missing: &T,
+) {
+    let n_coords = columns.first().map_or(0, |c| c.len());
+    let is_present: Vec<bool> = (0..n_coords)
+        .map(|idx| !columns.iter().any(|col| col[idx].is_nan()))
+        .collect();
+    let n_present = is_present.iter().filter(|p| **p).count();
+    if n_present == n_coords {
+        indexer.column_get_into(columns, buf);
+        return;
+    }
+    let mut values = vec![missing.clone(); n_present];
+    if n_present > 0 {
+        let present_cols: Vec<Vec<f64>> = columns
+            .iter()
+            .map(|col| {
+                col.iter()
+                    .zip(is_present.iter())
+                    .filter_map(|(c, p)| p.then_some(*c))
+                    .collect()
+            })
+            .collect();
+        let refs: Vec<&[f64]> = present_cols.iter().map(|c| c.as_ref()).collect();
+        indexer.column_get_into(&refs, &mut values);
+    }
+    let mut values = values.into_iter();
+    for (p, b) in is_present.into_iter().zip(buf.iter_mut()) {
+        *b = if p {
+            values.next().expect("one value per present coordinate")
+        } else {
+            missing.clone()
+        };
+    }
+}
+
+/// Look up values at transformed coordinates.
+///
+/// Coordinates which transform to NaN (e.g. outside of a displacement field's domain)
+/// are treated as missing: they are not looked up, and take the [missing value](Transformed::with_missing).
 pub struct Transformed<T, R: RealIndex<T>> {
     indexer: R,
     transform: Arc<dyn Transformation>,
-    _t: PhantomData<T>,
+    missing: T,
 }
 
-impl<T, R: RealIndex<T>> Transformed<T, R> {
+impl<T: Default, R: RealIndex<T>> Transformed<T, R> {
+    /// The missing value defaults to `T::default()`.
     pub fn try_new(indexer: R, transform: Arc<dyn Transformation>) -> Result<Self, String> {
         if transform.output_ndim() != indexer.ndim() {
             return Err("Dimensionality mismatch".into());
@@ -287,15 +401,30 @@ impl<T, R: RealIndex<T>> Transformed<T, R> {
         Ok(Self {
             indexer,
             transform,
-            _t: Default::default(),
+            missing: Default::default(),
         })
     }
 }
 
-impl<T, R: RealIndex<T>> RealIndex<T> for Transformed<T, R> {
+impl<T, R: RealIndex<T>> Transformed<T, R> {
+    /// Set the value for coordinates which transform to NaN.
+    pub fn with_missing(mut self, missing: T) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    pub fn missing(&self) -> &T {
+        &self.missing
+    }
+}
+
+impl<T: Clone, R: RealIndex<T>> RealIndex<T> for Transformed<T, R> {
     fn get(&self, coord: &[f64]) -> T {
         let mut new_coord: ShortVec<f64> = smallvec![f64::NAN; self.transform.output_ndim()];
         self.transform.transform_into(coord, &mut new_coord);
+        if is_missing(&new_coord) {
+            return self.missing.clone();
+        }
         self.indexer.get(&new_coord)
     }
 
@@ -303,19 +432,151 @@ impl<T, R: RealIndex<T>> RealIndex<T> for Transformed<T, R> {
         let mut ravelled = Ravelled::new_full(self.transform.output_ndim(), coords.len(), f64::NAN);
         self.transform
             .bulk_transform_into(coords, &mut ravelled.chunks_mut().collect::<Vec<_>>());
-        self.indexer
-            .bulk_get_into(&ravelled.chunks().collect::<Vec<_>>(), buf);
+        bulk_get_present(
+            &self.indexer,
+            &ravelled.chunks().collect::<Vec<_>>(),
+            buf,
+            &self.missing,
+        );
     }
 
     fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
-        let mut ravelled = Ravelled::new_full(columns[0].len(), columns.len(), f64::NAN);
+        let mut ravelled =
+            Ravelled::new_full(columns[0].len(), self.transform.output_ndim(), f64::NAN);
         self.transform
             .column_transform_into(columns, &mut ravelled.chunks_mut().collect::<Vec<_>>());
-        self.indexer
-            .column_get_into(&ravelled.chunks().collect::<Vec<_>>(), buf);
+        column_get_present(
+            &self.indexer,
+            &ravelled.chunks().collect::<Vec<_>>(),
+            buf,
+            &self.missing,
+        );
     }
 
     fn ndim(&self) -> usize {
         self.transform.input_ndim()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The sum of the coordinate's components; panics on NaN.
+    struct Sum;
+
+    impl RealIndex<f64> for Sum {
+        fn get(&self, coord: &[f64]) -> f64 {
+            assert!(!is_missing(coord), "missing coordinate was looked up");
+            coord.iter().sum()
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_real_column_get_into() {
+        // every output is written, not just the first
+        let columns = [[1.0, 3.0, 0.0], [0.5, 0.5, 4.0]];
+        let columns: Vec<&[f64]> = columns.iter().map(|c| c.as_slice()).collect();
+        let mut buf = [f64::NAN; 3];
+        Sum.column_get_into(&columns, &mut buf);
+        assert_eq!(buf, [1.5, 3.5, 4.0]);
+    }
+
+    /// Identity where the first component is less than 2, NaN elsewhere.
+    #[derive(Debug)]
+    struct Truncate;
+
+    impl Transformation for Truncate {
+        fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
+            if pt[0] < 2.0 {
+                buf.copy_from_slice(pt);
+            } else {
+                buf.fill(f64::NAN);
+            }
+        }
+
+        fn invert(&self) -> Option<Arc<dyn Transformation>> {
+            None
+        }
+
+        fn is_identity(&self) -> bool {
+            false
+        }
+
+        fn input_ndim(&self) -> usize {
+            2
+        }
+
+        fn output_ndim(&self) -> usize {
+            2
+        }
+    }
+
+    /// Drops the last of three components.
+    #[derive(Debug)]
+    struct Project;
+
+    impl Transformation for Project {
+        fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
+            buf.copy_from_slice(&pt[..2]);
+        }
+
+        fn invert(&self) -> Option<Arc<dyn Transformation>> {
+            None
+        }
+
+        fn is_identity(&self) -> bool {
+            false
+        }
+
+        fn input_ndim(&self) -> usize {
+            3
+        }
+
+        fn output_ndim(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_transformed_ndim() {
+        // columns are transformed into the output dimensionality, not the input's
+        let t = Transformed::try_new(Sum, Arc::new(Project)).unwrap();
+        let columns = [[1.0, 3.0], [0.5, 0.5], [10.0, 20.0]];
+        let columns: Vec<&[f64]> = columns.iter().map(|c| c.as_slice()).collect();
+        let mut buf = [f64::NAN; 2];
+        t.column_get_into(&columns, &mut buf);
+        assert_eq!(buf, [1.5, 3.5]);
+
+        let pts = [[1.0, 0.5, 10.0], [3.0, 0.5, 20.0]];
+        let coords: Vec<&[f64]> = pts.iter().map(|p| p.as_slice()).collect();
+        let mut buf = [f64::NAN; 2];
+        t.bulk_get_into(&coords, &mut buf);
+        assert_eq!(buf, [1.5, 3.5]);
+    }
+
+    #[test]
+    fn test_transformed_missing() {
+        let t = Transformed::try_new(Sum, Arc::new(Truncate))
+            .unwrap()
+            .with_missing(-1.0);
+        assert_eq!(t.get(&[1.0, 0.5]), 1.5);
+        assert_eq!(t.get(&[3.0, 0.5]), -1.0);
+
+        let pts = [[1.0, 0.5], [3.0, 0.5], [0.0, 4.0]];
+        let coords: Vec<&[f64]> = pts.iter().map(|p| p.as_slice()).collect();
+        let mut buf = [f64::NAN; 3];
+        t.bulk_get_into(&coords, &mut buf);
+        assert_eq!(buf, [1.5, -1.0, 4.0]);
+
+        let columns = [[1.0, 3.0, 0.0], [0.5, 0.5, 4.0]];
+        let columns: Vec<&[f64]> = columns.iter().map(|c| c.as_slice()).collect();
+        let mut buf = [f64::NAN; 3];
+        t.column_get_into(&columns, &mut buf);
+        assert_eq!(buf, [1.5, -1.0, 4.0]);
+    }
+}
@@ -0,0 +1,164 @@
+use std::marker::PhantomData;
+
+use smallvec::SmallVec;
+
+use crate::indexer::value::{
+    RealIndex, UnboundedIndex,
+    kernel::{self, LinearKernel, Reduce},
+};
+
+/// Majority-vote interpolation between the 2^N integer neighbours of each coordinate,
+/// for label images where interpolated values must be one of the labels present.
+///
+/// Each neighbour has its N-linear interpolation weight; neighbours with zero weight do not vote,
+/// so sample points return their own label.
+/// By default, every other neighbour has one vote, and ties go to the label with the greater total weight.
+/// If [weighted](Mode::new_weighted), the label with the greatest total weight wins.
+/// Coordinates which are infinite or NaN give the default label.
+pub struct Mode<T, U: UnboundedIndex<T>> {
+    unbounded: U,
+    weighted: bool,
+    _t: PhantomData<T>,
+}
+
+impl<T, U: UnboundedIndex<T>> Mode<T, U> {
+    pub fn new(unbounded: U) -> Self {
+        Self {
+            unbounded,
+            weighted: false,
+            _t: Default::default(),
+        }
+    }
+
+    pub fn new_weighted(unbounded: U) -> Self {
+        Self {
+            weighted: true,
+            ..Self::new(unbounded)
+        }
+    }
+
+    pub fn is_weighted(&self) -> bool {
+        self.weighted
+    }
+}
+
+impl<T, U: UnboundedIndex<T>> From<U> for Mode<T, U> {
+    fn from(value: U) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Tally of votes for each label: the label, its number of votes, and its total weight.
+type Tally<T> = SmallVec<[(T, usize, f64); 8]>;
+
+struct Vote {
+    weighted: bool,
+}
+
+impl<T: Copy + Default + Eq> Reduce<T, T> for Vote {
+    fn reduce(&self, weights: &[f64], values: &[T]) -> T {
+        let mut tally: Tally<T> = SmallVec::new();
+        for (w, v) in weights.iter().zip(values) {
+            if *w == 0.0 {
+                continue;
+            }
+            match tally.iter_mut().find(|(label, _, _)| label == v) {
+                Some((_, count, total)) => {
+                    *count += 1;
+                    *total += w;
+                }
+                None => tally.push((*v, 1, *w)),
+            }
+        }
+        let best = if self.weighted {
+            tally.into_iter().max_by(|a, b| a.2.total_cmp(&b.2))
+        } else {
+            tally
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then(a.2.total_cmp(&b.2)))
+        };
+        best.map(|(label, _, _)| label).unwrap_or_default()
+    }
+
+    fn missing(&self) -> T {
+        T::default()
+    }
+}
+
+impl<T: Copy + Default + Eq, U: UnboundedIndex<T>> RealIndex<T> for Mode<T, U> {
+    fn get(&self, coord: &[f64]) -> T {
+        let mut buf = [T::default()];
+        self.bulk_get_into(&[coord], &mut buf);
+        buf[0]
+    }
+
+    fn bulk_get_into(&self, coords: &[&[f64]], buf: &mut [T]) {
+        let vote = Vote {
+            weighted: self.weighted,
+        };
+        kernel::bulk_reduce_into(&LinearKernel, &vote, &self.unbounded, coords, buf);
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], buf: &mut [T]) {
+        let vote = Vote {
+            weighted: self.weighted,
+        };
+        kernel::column_reduce_into(&LinearKernel, &vote, &self.unbounded, columns, buf);
+    }
+
+    fn ndim(&self) -> usize {
+        self.unbounded.ndim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Label 1 at (0, 0), label 2 at (1, 1) and (1, 0), label 3 at (0, 1).
+    struct Labels;
+
+    impl UnboundedIndex<u32> for Labels {
+        fn get(&self, coord: &[isize]) -> u32 {
+            match (coord[0], coord[1]) {
+                (0, 0) => 1,
+                (1, _) => 2,
+                (0, 1) => 3,
+                _ => 0,
+            }
+        }
+
+        fn ndim(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_samples_keep_label() {
+        let mode = Mode::new(Labels);
+        assert_eq!(mode.get(&[0.0, 0.0]), 1);
+        assert_eq!(mode.get(&[0.0, 1.0]), 3);
+    }
+
+    #[test]
+    fn test_majority() {
+        // label 2 has two votes despite being further away
+        let mode = Mode::new(Labels);
+        assert_eq!(mode.get(&[0.2, 0.3]), 2);
+        // on the edge between (0, 0) and (0, 1), tie broken by weight
+        assert_eq!(mode.get(&[0.0, 0.4]), 1);
+    }
+
+    #[test]
+    fn test_weighted() {
+        let mode = Mode::new_weighted(Labels);
+        // weights: label 1 0.56, label 2 0.14 + 0.06, label 3 0.24
+        assert_eq!(mode.get(&[0.2, 0.3]), 1);
+        // weights: label 1 0.06, label 2 0.24 + 0.56, label 3 0.14
+        assert_eq!(mode.get(&[0.8, 0.7]), 2);
+
+        let mut buf = [0; 2];
+        mode.column_get_into(&[&[0.2, 0.8], &[0.3, 0.7]], &mut buf);
+        assert_eq!(buf, [1, 2]);
+    }
+}
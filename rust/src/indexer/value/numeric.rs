@@ -0,0 +1,60 @@
+/// Conversion of pixel values to and from `f64`, for interpolators which need to do arithmetic.
+///
+/// Integer conversions from `f64` round to the nearest value and saturate at the type's bounds;
+/// NaN becomes 0.
+pub trait Numeric: Copy + Default {
+    fn to_f64(self) -> f64;
+    fn from_f64(val: f64) -> Self;
+}
+
+macro_rules! impl_numeric_int {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(val: f64) -> Self {
+                    val.round() as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Numeric for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(val: f64) -> Self {
+        val as f32
+    }
+}
+
+impl Numeric for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(val: f64) -> Self {
+        val
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int_conversion() {
+        assert_eq!(u8::from_f64(1.5), 2);
+        assert_eq!(u8::from_f64(-3.0), 0);
+        assert_eq!(u8::from_f64(300.0), 255);
+        assert_eq!(i16::from_f64(-2.6), -3);
+        assert_eq!(u16::from_f64(f64::NAN), 0);
+    }
+}
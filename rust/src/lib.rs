@@ -12,7 +12,10 @@ mod matrix;
 pub use matrix::{Matrix, MatrixBuilder};
 use smallvec::smallvec;
 mod graph;
-pub use graph::{Edge, TransformGraph};
+pub use graph::{
+    Axis, AxisType, Edge, EdgeOrigin, FrozenTransformGraph, GraphPath, LoopClosure, LoopResidual,
+    Samples, TransformGraph, namespaced, unit_conversion_factor,
+};
 pub mod indexer;
 pub mod ndarr;
 
@@ -48,6 +48,14 @@ impl Matrix {
         mat
     }
 
+    pub fn new_zeros(nrows: usize, ncols: usize) -> Self {
+        Self {
+            data: vec![0.0; nrows * ncols],
+            nrows,
+            ncols,
+        }
+    }
+
     /// Row-major/ C order data
     pub fn try_new(data: Vec<f64>, ncols: usize) -> Result<Self, String> {
         // TODO: check homogeneity
@@ -125,6 +133,26 @@ impl Matrix {
         }
     }
 
+    /// Matrix product `self * other`.
+    pub fn matmul_matrix(&self, other: &Matrix) -> Result<Matrix, String> {
+        if self.ncols != other.nrows {
+            return Err(format!(
+                "Matrix: cannot multiply {}x{} by {}x{}",
+                self.nrows, self.ncols, other.nrows, other.ncols
+            ));
+        }
+        let mut out = Matrix::new_zeros(self.nrows, other.ncols);
+        for r in 0..self.nrows {
+            for k in 0..self.ncols {
+                let val = self[(r, k)];
+                for c in 0..other.ncols {
+                    out[(r, c)] += val * other[(k, c)];
+                }
+            }
+        }
+        Ok(out)
+    }
+
     pub fn get(&self, row: usize, col: usize) -> Option<&f64> {
         self.data.get(row * self.ncols + col)
     }
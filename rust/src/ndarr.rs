@@ -39,7 +39,7 @@ impl Layout for ColumnMajor {
                 return None;
             }
             total += i * prev_s;
-            prev_s = *s;
+            prev_s *= s;
         }
         Some(total)
     }
@@ -83,7 +83,7 @@ impl Layout for RowMajor {
                 return None;
             }
             total += i * prev_s;
-            prev_s = *s;
+            prev_s *= s;
         }
         Some(total)
     }
@@ -153,3 +153,31 @@ impl<T, L: Layout> AsMut<[T]> for VecNdArray<T, L> {
         self.data.as_mut_slice()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_idx_3d() {
+        let shape = [2, 3, 4];
+        let row = RowMajor::new(&shape);
+        let col = ColumnMajor::new(&shape);
+        let mut expected = 0;
+        for i in 0..2 {
+            for j in 0..3 {
+                for k in 0..4 {
+                    assert_eq!(row.linear_idx(&[i, j, k]), Some(expected));
+                    assert_eq!(col.linear_idx(&[i, j, k]), Some(i + 2 * j + 6 * k));
+                    expected += 1;
+                }
+            }
+        }
+        assert_eq!(row.linear_idx(&[1, 3, 0]), None);
+        assert_eq!(col.linear_idx(&[0, 0, 4]), None);
+
+        let arr = VecNdArray::new((0..24).collect(), row).unwrap();
+        assert_eq!(arr.get(&[1, 2, 3]), Some(23));
+        assert_eq!(arr.get(&[1, 0, 2]), Some(14));
+    }
+}
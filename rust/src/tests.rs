@@ -1,6 +1,6 @@
 use std::{iter, sync::LazyLock};
 
-use crate::Transformation;
+use crate::{Transformation, traits::finite_difference_jacobian};
 use faer::rand::{Rng, SeedableRng, rngs::SmallRng};
 
 pub const SMALL_NUMBER: f64 = 1e-10;
@@ -96,6 +96,40 @@ pub fn check_transform_col<T: Transformation>(t: T) {
     }
 }
 
+/// Assert that two transformations give the same results for the same coordinates (more or less).
+pub fn check_equivalent(t1: &dyn Transformation, t2: &dyn Transformation) {
+    init_logger();
+    assert_eq!(t1.input_ndim(), t2.input_ndim());
+    assert_eq!(t1.output_ndim(), t2.output_ndim());
+
+    let coords: &[Vec<f64>] = COORDS_3D_1000.as_ref();
+    let mut out1 = vec![f64::NAN; t1.output_ndim()];
+    let mut out2 = vec![f64::NAN; t2.output_ndim()];
+    for pt in coords.iter() {
+        t1.transform_into(pt, &mut out1);
+        t2.transform_into(pt, &mut out2);
+        approx::assert_ulps_eq!(out1.as_slice(), out2.as_slice(), epsilon = SMALL_NUMBER);
+    }
+}
+
+/// Assert that a transformation's Jacobian matches a finite-difference estimate (more or less).
+pub fn check_jacobian<T: Transformation>(t: T) {
+    init_logger();
+    let len = t.input_ndim() * t.output_ndim();
+    let mut actual = vec![f64::NAN; len];
+    let mut expected = vec![f64::NAN; len];
+    for pt in COORDS_3D_1000.iter().take(20) {
+        t.jacobian_into(pt, &mut actual);
+        finite_difference_jacobian(&t, pt, &mut expected);
+        approx::assert_relative_eq!(
+            actual.as_slice(),
+            expected.as_slice(),
+            epsilon = 1e-6,
+            max_relative = 1e-6
+        );
+    }
+}
+
 /// Assert that inverting a transformation recovers the original coordinate (more or less).
 pub fn check_inverse_transform_coord<T: Transformation>(t: T) {
     init_logger();
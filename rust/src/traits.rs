@@ -1,11 +1,14 @@
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
 /// Core spatial transformation interface.
 ///
 /// Implementations may not perform any bounds checks on the input,
 /// as these transformations generally happen in performance-critical hot loops.
 /// Therefore, they may panic if coordinates or output buffers of incorrect length are given.
-pub trait Transformation: std::fmt::Debug + Send + Sync {
+///
+/// Implementors must be `'static` (via the [Any] supertrait),
+/// so that trait objects can be inspected with `downcast_ref`.
+pub trait Transformation: Any + std::fmt::Debug + Send + Sync {
     /// Transform a single point from the input space to the output space.
     /// Writes to a pre-allocated output buffer.
     fn transform_into(&self, pt: &[f64], buf: &mut [f64]);
@@ -63,8 +66,91 @@ pub trait Transformation: std::fmt::Debug + Send + Sync {
     fn input_ndim(&self) -> usize;
 
     fn output_ndim(&self) -> usize;
+
+    /// The relative cost of transforming a single point,
+    /// roughly in floating-point operations.
+    /// Composite transformations sum the costs of their parts.
+    ///
+    /// By default, this is the cost of a dense linear transformation,
+    /// i.e. a multiply and an add for each input dimension of each output dimension;
+    /// specific transformations may override it.
+    fn estimated_cost(&self) -> f64 {
+        2.0 * (self.input_ndim() * self.output_ndim()) as f64
+    }
+
+    /// Write the Jacobian of the transformation at the given point into a pre-allocated buffer:
+    /// the partial derivative of each output dimension (rows) with respect to each input dimension (columns),
+    /// in row-major order.
+    ///
+    /// By default, this is estimated by central finite differences;
+    /// specific transformations may override it with an exact value.
+    fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) {
+        finite_difference_jacobian(self, pt, buf);
+    }
+
+    /// A short, human-readable name for the kind of transformation, e.g. for visualisation.
+    ///
+    /// By default, this is the name of the concrete type, without its module path or generics.
+    fn kind(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+impl dyn Transformation {
+    /// Get a reference to the concrete transformation type, if it is of that type.
+    pub fn downcast_ref<T: Transformation>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+
+    /// Whether the concrete transformation is of the given type.
+    pub fn is<T: Transformation>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
 }
 
+/// Relative step size for estimating derivatives by finite differences.
+const FINITE_DIFFERENCE_STEP: f64 = 1e-6;
+
+/// Estimate the Jacobian of a transformation at a point by central finite differences,
+/// writing it row-major into `buf`.
+pub(crate) fn finite_difference_jacobian<T: Transformation + ?Sized>(
+    t: &T,
+    pt: &[f64],
+    buf: &mut [f64],
+) {
+    finite_difference(|p, b| t.transform_into(p, b), t.output_ndim(), pt, buf);
+}
+
+/// Estimate the Jacobian of a function with `out_ndim` outputs at a point by central finite differences,
+/// writing it row-major into `buf`.
+pub(crate) fn finite_difference(
+    f: impl Fn(&[f64], &mut [f64]),
+    out_ndim: usize,
+    pt: &[f64],
+    buf: &mut [f64],
+) {
+    let in_ndim = pt.len();
+    let mut shifted = pt.to_vec();
+    let mut above = vec![f64::NAN; out_ndim];
+    let mut below = vec![f64::NAN; out_ndim];
+    for col in 0..in_ndim {
+        let step = FINITE_DIFFERENCE_STEP * pt[col].abs().max(1.0);
+        shifted[col] = pt[col] + step;
+        f(&shifted, &mut above);
+        shifted[col] = pt[col] - step;
+        f(&shifted, &mut below);
+        shifted[col] = pt[col];
+        for (row, (a, b)) in above.iter().zip(below.iter()).enumerate() {
+            buf[row * in_ndim + col] = (a - b) / (2.0 * step);
+        }
+    }
+}
+
+/// Default relative cost of looking up a single value in an [ArrayProvider].
+const ARRAY_LOOKUP_COST: f64 = 50.0;
+
 /// Trait for a type which, given a coordinate as an input,
 /// will return an array of values by writing into a pre-allocated buffer.
 ///
@@ -113,6 +199,23 @@ pub trait ArrayProvider: std::fmt::Debug + Send + Sync {
     fn index_len(&self) -> usize;
 
     fn output_len(&self) -> usize;
+
+    /// The extent of each axis of the provider's index space, if it has a bounded domain:
+    /// index coordinates from 0 to `extent - 1` are within the domain.
+    ///
+    /// By default, providers are considered to be defined everywhere.
+    fn domain(&self) -> Option<&[usize]> {
+        None
+    }
+
+    /// The relative cost of a single lookup,
+    /// in the same units as [Transformation::estimated_cost].
+    ///
+    /// By default, this assumes that each output value is fetched from memory
+    /// and interpolated, which is far more expensive than arithmetic.
+    fn estimated_cost(&self) -> f64 {
+        ARRAY_LOOKUP_COST * self.output_len() as f64
+    }
 }
 
 /// Trait representing an N-D array to look up (or interpolate) a value into.
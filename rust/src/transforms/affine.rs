@@ -1,6 +1,10 @@
-use smallvec::ToSmallVec;
+use smallvec::{ToSmallVec, smallvec};
 
-use crate::{ShortVec, Transformation, matrix::Matrix};
+use crate::{
+    ShortVec, Transformation,
+    matrix::Matrix,
+    transforms::{Bijection, ByDimension, Identity, MapAxis, Rotation, Scale, Sequence, Translate},
+};
 
 #[derive(Debug, Clone)]
 pub struct Affine {
@@ -73,6 +77,107 @@ impl Affine {
             translation,
         })
     }
+
+    /// The unaugmented matrix, with M rows and N columns for a transform from N to M dimensions.
+    pub fn matrix(&self) -> &Matrix {
+        &self.unaugmented
+    }
+
+    pub fn translation(&self) -> &[f64] {
+        &self.translation
+    }
+
+    /// Represent a linear transformation as an Affine, if possible.
+    ///
+    /// Supports [Identity], [Scale], [Translate], [MapAxis], [Rotation] and [Affine],
+    /// as well as [Sequence]s, [ByDimension]s and [Bijection]s composed only of those.
+    /// Returns `None` for any other transformation.
+    pub fn from_transform(t: &dyn Transformation) -> Option<Self> {
+        if let Some(a) = t.downcast_ref::<Affine>() {
+            return Some(a.clone());
+        }
+        if t.is::<Identity>() {
+            return Some(Self::new_identity(t.input_ndim()));
+        }
+        if let Some(s) = t.downcast_ref::<Scale>() {
+            let mut aff = Self::new_identity(s.input_ndim());
+            for (idx, f) in s.factors().iter().enumerate() {
+                aff.unaugmented[(idx, idx)] = *f;
+            }
+            return Some(aff);
+        }
+        if let Some(tr) = t.downcast_ref::<Translate>() {
+            let mut aff = Self::new_identity(tr.input_ndim());
+            aff.translation.copy_from_slice(tr.translation());
+            return Some(aff);
+        }
+        if let Some(m) = t.downcast_ref::<MapAxis>() {
+            let ndim = m.input_ndim();
+            let mut unaugmented = Matrix::new_zeros(ndim, ndim);
+            for (out_idx, in_idx) in m.map().iter().enumerate() {
+                unaugmented[(out_idx, *in_idx)] = 1.0;
+            }
+            return Some(Self {
+                unaugmented,
+                translation: smallvec![0.0; ndim],
+            });
+        }
+        if let Some(r) = t.downcast_ref::<Rotation>() {
+            return Some(Self {
+                unaugmented: r.matrix().clone(),
+                translation: smallvec![0.0; r.output_ndim()],
+            });
+        }
+        if let Some(b) = t.downcast_ref::<Bijection>() {
+            return Self::from_transform(b.forward().as_ref());
+        }
+        if let Some(seq) = t.downcast_ref::<Sequence>() {
+            let mut iter = seq.transforms().iter();
+            let mut aff = Self::from_transform(iter.next()?.as_ref())?;
+            for inner in iter {
+                aff = aff.then(&Self::from_transform(inner.as_ref())?).ok()?;
+            }
+            return Some(aff);
+        }
+        if let Some(bd) = t.downcast_ref::<ByDimension>() {
+            let mut unaugmented = Matrix::new_zeros(bd.output_ndim(), bd.input_ndim());
+            let mut translation: ShortVec<f64> = smallvec![0.0; bd.output_ndim()];
+            for (inner, in_dims, out_dims) in bd.sub_transforms() {
+                let sub = Self::from_transform(inner.as_ref())?;
+                for (sub_r, r) in out_dims.iter().enumerate() {
+                    for (sub_c, c) in in_dims.iter().enumerate() {
+                        unaugmented[(*r, *c)] = sub.unaugmented[(sub_r, sub_c)];
+                    }
+                    translation[*r] = sub.translation[sub_r];
+                }
+            }
+            return Some(Self {
+                unaugmented,
+                translation,
+            });
+        }
+        None
+    }
+
+    fn new_identity(ndim: usize) -> Self {
+        Self {
+            unaugmented: Matrix::new_identity(ndim),
+            translation: smallvec![0.0; ndim],
+        }
+    }
+
+    /// Compose two affines into one which applies `self` and then `next`.
+    pub fn then(&self, next: &Affine) -> Result<Self, String> {
+        let unaugmented = next.unaugmented.matmul_matrix(&self.unaugmented)?;
+        let mut translation = next.unaugmented.matmul(&self.translation);
+        for (t, next_t) in translation.iter_mut().zip(next.translation.iter()) {
+            *t += next_t;
+        }
+        Ok(Self {
+            unaugmented,
+            translation,
+        })
+    }
 }
 
 impl Transformation for Affine {
@@ -96,6 +201,17 @@ impl Transformation for Affine {
         self.unaugmented.nrows()
     }
 
+    fn jacobian_into(&self, _pt: &[f64], buf: &mut [f64]) {
+        let ncols = self.unaugmented.ncols();
+        for (idx, b) in buf.iter_mut().enumerate() {
+            *b = self.unaugmented[(idx / ncols, idx % ncols)];
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        (2 * self.unaugmented.nrows() * self.unaugmented.ncols() + self.translation.len()) as f64
+    }
+
     fn invert(&self) -> Option<std::sync::Arc<dyn Transformation>> {
         // todo
         None
@@ -125,7 +241,8 @@ mod tests {
         Matrix,
         tests::{
             check_inverse_transform_bulk, check_inverse_transform_col,
-            check_inverse_transform_coord, check_transform_bulk, check_transform_col,
+            check_inverse_transform_coord, check_jacobian, check_transform_bulk,
+            check_transform_col,
         },
     };
 
@@ -164,4 +281,9 @@ mod tests {
     fn test_inverse_columns() {
         check_inverse_transform_col(make_transform());
     }
+
+    #[test]
+    fn test_jacobian() {
+        check_jacobian(make_transform());
+    }
 }
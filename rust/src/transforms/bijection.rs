@@ -29,6 +29,14 @@ impl Bijection {
     ) -> Result<Self, String> {
         Self::try_new_arc(Arc::new(forward), Arc::new(reverse))
     }
+
+    pub fn forward(&self) -> &Arc<dyn Transformation> {
+        &self.forward
+    }
+
+    pub fn reverse(&self) -> &Arc<dyn Transformation> {
+        &self.reverse
+    }
 }
 
 impl Transformation for Bijection {
@@ -47,6 +55,14 @@ impl Transformation for Bijection {
         self.forward.output_ndim()
     }
 
+    fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) {
+        self.forward.jacobian_into(pt, buf);
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.forward.estimated_cost()
+    }
+
     fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
         self.forward.transform_into(pt, buf);
     }
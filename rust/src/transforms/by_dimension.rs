@@ -13,6 +13,16 @@ impl ByDimension {
     pub fn builder(in_ndim: usize, out_ndim: usize) -> ByDimensionBuilder {
         ByDimensionBuilder::new(in_ndim, out_ndim)
     }
+
+    /// Iterate over the inner transformations,
+    /// along with the input dimensions they read from and output dimensions they write to.
+    pub fn sub_transforms(
+        &self,
+    ) -> impl Iterator<Item = (&Arc<dyn Transformation>, &[usize], &[usize])> {
+        self.0
+            .iter()
+            .map(|st| (&st.transform, st.in_dims.as_slice(), st.out_dims.as_slice()))
+    }
 }
 
 #[derive(Debug)]
@@ -158,6 +168,32 @@ impl Transformation for ByDimension {
         self.0.iter().map(|bt| bt.out_dims.len()).sum()
     }
 
+    fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) {
+        let in_ndim = pt.len();
+        buf.fill(0.0);
+        let mut ordered_pt: ShortVec<f64> = smallvec![f64::NAN; in_ndim];
+        let mut block = vec![];
+        for bt in self.0.iter() {
+            let (sub_in, sub_out) = (bt.in_dims.len(), bt.out_dims.len());
+            for (i, o) in bt.in_dims.iter().zip(ordered_pt.iter_mut()) {
+                *o = pt[*i];
+            }
+            block.clear();
+            block.resize(sub_in * sub_out, 0.0);
+            bt.transform
+                .jacobian_into(&ordered_pt[..sub_in], &mut block);
+            for (row, out_dim) in bt.out_dims.iter().enumerate() {
+                for (col, in_dim) in bt.in_dims.iter().enumerate() {
+                    buf[out_dim * in_ndim + in_dim] = block[row * sub_in + col];
+                }
+            }
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.0.iter().map(|s| s.transform.estimated_cost()).sum()
+    }
+
     fn is_identity(&self) -> bool {
         for st in self.0.iter() {
             if !st.transform.is_identity() {
@@ -321,7 +357,7 @@ mod tests {
     use super::ByDimension;
     use crate::tests::{
         check_inverse_transform_bulk, check_inverse_transform_col, check_inverse_transform_coord,
-        check_transform_bulk, check_transform_col, init_logger,
+        check_jacobian, check_transform_bulk, check_transform_col, init_logger,
     };
     use crate::{
         Transformation, as_muts, as_refs,
@@ -383,4 +419,9 @@ mod tests {
     fn test_inverse_columns() {
         check_inverse_transform_col(make_transform());
     }
+
+    #[test]
+    fn test_jacobian() {
+        check_jacobian(make_transform());
+    }
 }
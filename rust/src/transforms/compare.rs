@@ -0,0 +1,412 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{
+    Transformation,
+    transforms::{Affine, Bijection, ByDimension, Sequence, simplify},
+};
+
+/// A canonical representation of a transformation, used for comparison and hashing.
+#[derive(Debug)]
+enum Canonical {
+    /// Any transformation which can be represented as an [Affine].
+    Linear(Affine),
+    /// A sequence in which no two consecutive transformations are linear.
+    Sequence(Vec<Canonical>),
+    /// Sub-transformations sorted by their output dimensions.
+    ByDimension(Vec<(Canonical, Vec<usize>, Vec<usize>)>),
+    /// Forward and reverse transformations, which are given explicitly so may be inconsistent.
+    Bijection(Box<Canonical>, Box<Canonical>),
+    /// Transformations whose parameters cannot be inspected, compared by identity.
+    Opaque(Arc<dyn Transformation>),
+}
+
+impl Canonical {
+    fn new(t: &Arc<dyn Transformation>) -> Self {
+        Self::from_simplified(&simplify(t.clone()))
+    }
+
+    fn from_simplified(t: &Arc<dyn Transformation>) -> Self {
+        // before the affine check, which only sees a bijection's forward transformation
+        if let Some(b) = t.downcast_ref::<Bijection>() {
+            return Self::Bijection(
+                Box::new(Self::from_simplified(b.forward())),
+                Box::new(Self::from_simplified(b.reverse())),
+            );
+        }
+        if let Some(aff) = Affine::from_transform(t.as_ref()) {
+            return Self::Linear(aff);
+        }
+        if let Some(seq) = t.downcast_ref::<Sequence>() {
+            let mut out: Vec<Canonical> = Vec::with_capacity(seq.transforms().len());
+            for inner in seq.transforms() {
+                let c = Self::from_simplified(inner);
+                // merge consecutive linear transformations
+                if let (Some(Self::Linear(prev)), Self::Linear(next)) = (out.last_mut(), &c)
+                    && let Ok(merged) = prev.then(next)
+                {
+                    *prev = merged;
+                    continue;
+                }
+                out.push(c);
+            }
+            if out.len() == 1 {
+                return out.pop().unwrap();
+            }
+            return Self::Sequence(out);
+        }
+        if let Some(bd) = t.downcast_ref::<ByDimension>() {
+            let mut subs: Vec<_> = bd
+                .sub_transforms()
+                .filter(|(_, in_dims, _)| !in_dims.is_empty())
+                .map(|(inner, in_dims, out_dims)| {
+                    (
+                        Self::from_simplified(inner),
+                        in_dims.to_vec(),
+                        out_dims.to_vec(),
+                    )
+                })
+                .collect();
+            subs.sort_by(|a, b| a.2.cmp(&b.2));
+            return Self::ByDimension(subs);
+        }
+        Self::Opaque(t.clone())
+    }
+
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        match (self, other) {
+            (Self::Linear(a), Self::Linear(b)) => affine_approx_eq(a, b, tolerance),
+            (Self::Sequence(a), Self::Sequence(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(a, b)| a.approx_eq(b, tolerance))
+            }
+            (Self::ByDimension(a), Self::ByDimension(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(a, b)| a.1 == b.1 && a.2 == b.2 && a.0.approx_eq(&b.0, tolerance))
+            }
+            (Self::Bijection(fa, ra), Self::Bijection(fb, rb)) => {
+                fa.approx_eq(fb, tolerance) && ra.approx_eq(rb, tolerance)
+            }
+            (Self::Opaque(a), Self::Opaque(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Self::Linear(aff) => {
+                0u8.hash(state);
+                let m = aff.matrix();
+                m.nrows().hash(state);
+                m.ncols().hash(state);
+                for r in 0..m.nrows() {
+                    for c in 0..m.ncols() {
+                        hash_f64(m[(r, c)], state);
+                    }
+                }
+                for t in aff.translation() {
+                    hash_f64(*t, state);
+                }
+            }
+            Self::Sequence(inner) => {
+                1u8.hash(state);
+                inner.len().hash(state);
+                for c in inner {
+                    c.hash(state);
+                }
+            }
+            Self::ByDimension(subs) => {
+                2u8.hash(state);
+                subs.len().hash(state);
+                for (c, in_dims, out_dims) in subs {
+                    in_dims.hash(state);
+                    out_dims.hash(state);
+                    c.hash(state);
+                }
+            }
+            Self::Bijection(forward, reverse) => {
+                3u8.hash(state);
+                forward.hash(state);
+                reverse.hash(state);
+            }
+            Self::Opaque(t) => {
+                4u8.hash(state);
+                (Arc::as_ptr(t) as *const ()).hash(state);
+            }
+        }
+    }
+}
+
+/// Hash a float such that `0.0` and `-0.0` hash the same.
+fn hash_f64<H: Hasher>(val: f64, state: &mut H) {
+    if val == 0.0 {
+        0u64.hash(state)
+    } else {
+        val.to_bits().hash(state)
+    }
+}
+
+fn affine_approx_eq(a: &Affine, b: &Affine, tolerance: f64) -> bool {
+    let (ma, mb) = (a.matrix(), b.matrix());
+    if ma.nrows() != mb.nrows() || ma.ncols() != mb.ncols() {
+        return false;
+    }
+    let close = |x: f64, y: f64| (x - y).abs() <= tolerance;
+    (0..ma.nrows()).all(|r| (0..ma.ncols()).all(|c| close(ma[(r, c)], mb[(r, c)])))
+        && a.translation()
+            .iter()
+            .zip(b.translation())
+            .all(|(x, y)| close(*x, *y))
+}
+
+/// Whether two transformations are equivalent, to within an absolute tolerance
+/// on each of their parameters.
+///
+/// Transformations are compared in a canonical form (see [simplify]),
+/// in which any linear transformation is represented as an [Affine].
+/// Therefore, transformations of different kinds can be equal,
+/// e.g. a [crate::transforms::Scale] and a diagonal [Affine].
+///
+/// A [Bijection] is compared by both its forward and reverse transformations.
+///
+/// Transformations whose parameters cannot be inspected,
+/// such as [crate::transforms::Coordinate] and [crate::transforms::Displacement],
+/// are only equal if they are the same instance.
+pub fn approx_eq(a: &Arc<dyn Transformation>, b: &Arc<dyn Transformation>, tolerance: f64) -> bool {
+    if Arc::ptr_eq(a, b) {
+        return true;
+    }
+    if a.input_ndim() != b.input_ndim() || a.output_ndim() != b.output_ndim() {
+        return false;
+    }
+    Canonical::new(a).approx_eq(&Canonical::new(b), tolerance)
+}
+
+/// Hash the canonical form of a transformation (see [approx_eq]).
+///
+/// Transformations which are exactly equal in canonical form have the same hash,
+/// regardless of their kind.
+/// Transformations which are only approximately equal may not.
+///
+/// # Opaque transformations
+///
+/// Transformations whose parameters cannot be inspected (see [approx_eq])
+/// are hashed by their address in memory.
+/// Hashes are therefore only consistent within a single run of a program,
+/// and only while the transformations they were computed from are alive:
+/// once one is dropped, a new transformation may be allocated at the same address
+/// and collide with a stored hash.
+/// Keep the transformations alongside any hashes which outlive a single comparison.
+pub fn structural_hash(t: &Arc<dyn Transformation>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    t.input_ndim().hash(&mut hasher);
+    t.output_ndim().hash(&mut hasher);
+    Canonical::new(t).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The Euclidean distance between the outputs of two transformations
+/// at each of the given points.
+///
+/// This is useful for comparing alternative routes between the same coordinate systems,
+/// e.g. those found by [crate::TransformGraph::k_shortest_paths].
+///
+/// Fails if the transformations' dimensionalities differ,
+/// or if any point does not match the input dimensionality.
+pub fn disagreement(
+    a: &dyn Transformation,
+    b: &dyn Transformation,
+    pts: &[&[f64]],
+) -> Result<Vec<f64>, String> {
+    if a.input_ndim() != b.input_ndim() || a.output_ndim() != b.output_ndim() {
+        return Err(format!(
+            "Transformations are {}D -> {}D and {}D -> {}D",
+            a.input_ndim(),
+            a.output_ndim(),
+            b.input_ndim(),
+            b.output_ndim()
+        ));
+    }
+    if let Some(pt) = pts.iter().find(|p| p.len() != a.input_ndim()) {
+        return Err(format!(
+            "Point is {}D; transformations are {}D",
+            pt.len(),
+            a.input_ndim()
+        ));
+    }
+    let ndim = a.output_ndim();
+    let mut out_a = vec![f64::NAN; pts.len() * ndim];
+    let mut out_b = out_a.clone();
+    {
+        let mut bufs: Vec<_> = out_a.chunks_mut(ndim).collect();
+        a.bulk_transform_into(pts, &mut bufs);
+        let mut bufs: Vec<_> = out_b.chunks_mut(ndim).collect();
+        b.bulk_transform_into(pts, &mut bufs);
+    }
+    Ok(out_a
+        .chunks(ndim)
+        .zip(out_b.chunks(ndim))
+        .map(|(pa, pb)| {
+            pa.iter()
+                .zip(pb)
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{approx_eq, disagreement, structural_hash};
+    use crate::{
+        ArrayProvider, Matrix, Transformation,
+        transforms::{
+            Affine, Bijection, ByDimension, Coordinate, Identity, MapAxis, Scale, Sequence,
+            Translate,
+        },
+    };
+
+    #[derive(Debug)]
+    struct ZeroProvider;
+
+    impl ArrayProvider for ZeroProvider {
+        fn get_into(&self, _pt: &[f64], buf: &mut [f64]) {
+            buf.fill(0.0);
+        }
+
+        fn index_len(&self) -> usize {
+            2
+        }
+
+        fn output_len(&self) -> usize {
+            2
+        }
+    }
+
+    fn diagonal_affine(diag: &[f64]) -> Arc<dyn Transformation> {
+        let mut data = vec![0.0; diag.len() * diag.len()];
+        for (idx, d) in diag.iter().enumerate() {
+            data[idx * diag.len() + idx] = *d;
+        }
+        let matrix = Matrix::try_new(data, diag.len()).unwrap();
+        Arc::new(Affine::try_new(matrix, &vec![0.0; diag.len()]).unwrap())
+    }
+
+    #[test]
+    fn test_scale_affine() {
+        let s: Arc<dyn Transformation> = Arc::new(Scale::try_new(&[2.0, 3.0]).unwrap());
+        let a = diagonal_affine(&[2.0, 3.0]);
+        assert!(approx_eq(&s, &a, 0.0));
+        assert_eq!(structural_hash(&s), structural_hash(&a));
+
+        let a2 = diagonal_affine(&[2.0, 3.0 + 1e-9]);
+        assert!(!approx_eq(&s, &a2, 0.0));
+        assert!(approx_eq(&s, &a2, 1e-6));
+        assert!(!approx_eq(&s, &diagonal_affine(&[2.0, 4.0]), 1e-6));
+    }
+
+    #[test]
+    fn test_sequence_affine() {
+        let mut builder = Sequence::builder();
+        builder
+            .add_transform(Scale::try_new(&[2.0, 3.0]).unwrap())
+            .unwrap()
+            .add_transform(Translate::try_new(&[1.0, -1.0]).unwrap())
+            .unwrap();
+        let seq: Arc<dyn Transformation> = Arc::new(builder.build().unwrap());
+
+        #[rustfmt::skip]
+        let data = vec![
+            2.0, 0.0, 1.0,
+            0.0, 3.0, -1.0,
+        ];
+        let aff: Arc<dyn Transformation> =
+            Arc::new(Affine::try_from_translated(&Matrix::try_new(data, 3).unwrap()).unwrap());
+        assert!(approx_eq(&seq, &aff, 1e-12));
+        assert_eq!(structural_hash(&seq), structural_hash(&aff));
+    }
+
+    #[test]
+    fn test_identities() {
+        let id: Arc<dyn Transformation> = Arc::new(Identity::new(3));
+        let map: Arc<dyn Transformation> = Arc::new(MapAxis::try_new(&[0, 1, 2]).unwrap());
+        assert!(approx_eq(&id, &map, 0.0));
+        assert_eq!(structural_hash(&id), structural_hash(&map));
+
+        let id2: Arc<dyn Transformation> = Arc::new(Identity::new(2));
+        assert!(!approx_eq(&id, &id2, 0.0));
+    }
+
+    #[test]
+    fn test_opaque() {
+        let c1: Arc<dyn Transformation> = Arc::new(Coordinate::new(ZeroProvider));
+        let c2: Arc<dyn Transformation> = Arc::new(Coordinate::new(ZeroProvider));
+        assert!(approx_eq(&c1, &c1.clone(), 0.0));
+        assert!(!approx_eq(&c1, &c2, 0.0));
+
+        let mut b1 = ByDimension::builder(3, 3);
+        b1.add_any(c1.clone(), &[0, 1], &[0, 1])
+            .unwrap()
+            .add_transform(Scale::try_new(&[2.0]).unwrap(), &[2], &[2])
+            .unwrap();
+        let mut b2 = ByDimension::builder(3, 3);
+        b2.add_transform(Scale::try_new(&[2.0]).unwrap(), &[2], &[2])
+            .unwrap()
+            .add_any(c1.clone(), &[0, 1], &[0, 1])
+            .unwrap();
+        let bd1: Arc<dyn Transformation> = Arc::new(b1.build().unwrap());
+        let bd2: Arc<dyn Transformation> = Arc::new(b2.build().unwrap());
+        assert!(approx_eq(&bd1, &bd2, 0.0));
+        assert_eq!(structural_hash(&bd1), structural_hash(&bd2));
+    }
+
+    #[test]
+    fn test_bijection() {
+        let scale =
+            |f: f64| -> Arc<dyn Transformation> { Arc::new(Scale::try_new(&[f, f]).unwrap()) };
+        let c: Arc<dyn Transformation> = Arc::new(Coordinate::new(ZeroProvider));
+        let b1: Arc<dyn Transformation> =
+            Arc::new(Bijection::try_new_arc(c.clone(), scale(2.0)).unwrap());
+        let b2: Arc<dyn Transformation> =
+            Arc::new(Bijection::try_new_arc(c.clone(), scale(2.0)).unwrap());
+        let b3: Arc<dyn Transformation> =
+            Arc::new(Bijection::try_new_arc(c.clone(), scale(3.0)).unwrap());
+        assert!(approx_eq(&b1, &b2, 0.0));
+        assert_eq!(structural_hash(&b1), structural_hash(&b2));
+        // same forward, different reverse
+        assert!(!approx_eq(&b1, &b3, 0.0));
+        assert_ne!(structural_hash(&b1), structural_hash(&b3));
+        assert!(!approx_eq(&b1, &c, 0.0));
+
+        // linear forward, different reverses
+        let l1: Arc<dyn Transformation> =
+            Arc::new(Bijection::try_new_arc(scale(2.0), scale(0.5)).unwrap());
+        let l2: Arc<dyn Transformation> =
+            Arc::new(Bijection::try_new_arc(scale(2.0), scale(0.25)).unwrap());
+        assert!(approx_eq(&l1, &l1.clone(), 0.0));
+        assert!(!approx_eq(&l1, &l2, 0.0));
+        assert_ne!(structural_hash(&l1), structural_hash(&l2));
+    }
+
+    #[test]
+    fn test_disagreement() {
+        let t1: Arc<dyn Transformation> = Arc::new(Translate::try_new(&[1.0, 0.0]).unwrap());
+        let t2: Arc<dyn Transformation> = Arc::new(Scale::try_new(&[2.0, 2.0]).unwrap());
+        let pts: [&[f64]; 3] = [&[0.0, 0.0], &[1.0, 0.0], &[-1.0, 0.0]];
+        let d = disagreement(t1.as_ref(), t2.as_ref(), &pts).unwrap();
+        assert_eq!(d, vec![1.0, 0.0, 2.0]);
+
+        let t3 = Identity::new(3);
+        assert!(disagreement(t1.as_ref(), &t3, &pts).is_err());
+        assert!(disagreement(t1.as_ref(), t2.as_ref(), &[&[1.0]]).is_err());
+    }
+}
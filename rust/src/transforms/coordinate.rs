@@ -1,32 +1,145 @@
 use std::sync::Arc;
 
-use crate::{Transformation, traits::ArrayProvider};
+use crate::{
+    Transformation,
+    traits::ArrayProvider,
+    transforms::{
+        OutOfDomain,
+        field::{FieldLookup, domain_error},
+    },
+};
 
-#[derive(Debug)]
+/// Look up the output coordinate of each input point in a coordinate field.
+#[derive(Debug, Clone)]
 pub struct Coordinate {
-    provider: Arc<dyn ArrayProvider>,
+    lookup: FieldLookup,
 }
 
 impl Coordinate {
     pub fn new_any(provider: Arc<dyn ArrayProvider>) -> Self {
-        Self { provider }
+        Self {
+            lookup: FieldLookup::new(provider),
+        }
     }
+
     pub fn new<P: ArrayProvider + 'static>(provider: P) -> Self {
         Self::new_any(Arc::new(provider))
     }
+
+    /// Map input points into the field's index space before lookup,
+    /// e.g. with the inverse of the field grid's scale and translation.
+    ///
+    /// Fails if the transform's output does not match the field's index dimensionality.
+    pub fn with_field_transform(
+        self,
+        field_transform: Arc<dyn Transformation>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            lookup: self.lookup.try_with_field_transform(field_transform)?,
+        })
+    }
+
+    /// The transform from input points into the field's index space, if it is not the identity.
+    pub fn field_transform(&self) -> Option<&Arc<dyn Transformation>> {
+        self.lookup.field_transform()
+    }
+
+    /// Set what happens to points outside the field's domain (by default, [OutOfDomain::Provider]).
+    ///
+    /// Fails for [OutOfDomain::Identity] if the input and output dimensionalities differ.
+    pub fn with_out_of_domain(self, out_of_domain: OutOfDomain) -> Result<Self, String> {
+        if out_of_domain == OutOfDomain::Identity && self.input_ndim() != self.output_ndim() {
+            return Err(
+                "Coordinate transform cannot be the identity outside its domain: input and output dimensionality differ"
+                    .into(),
+            );
+        }
+        Ok(Self {
+            lookup: self.lookup.with_out_of_domain(out_of_domain),
+        })
+    }
+
+    pub fn out_of_domain(&self) -> OutOfDomain {
+        self.lookup.out_of_domain()
+    }
+
+    /// As [Transformation::transform_into],
+    /// but fails if the point is outside the field's domain and the policy is [OutOfDomain::Error].
+    pub fn try_transform_into(&self, pt: &[f64], buf: &mut [f64]) -> Result<(), String> {
+        let in_domain = self.get_into(pt, buf);
+        domain_error(self.out_of_domain(), &[in_domain])
+    }
+
+    /// As [Transformation::bulk_transform_into],
+    /// but fails if any point is outside the field's domain and the policy is [OutOfDomain::Error].
+    pub fn try_bulk_transform_into(
+        &self,
+        pts: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+    ) -> Result<(), String> {
+        let mut in_domain = vec![true; pts.len()];
+        self.bulk_get_into(pts, bufs, &mut in_domain);
+        domain_error(self.out_of_domain(), &in_domain)
+    }
+
+    /// As [Transformation::column_transform_into],
+    /// but fails if any point is outside the field's domain and the policy is [OutOfDomain::Error].
+    pub fn try_column_transform_into(
+        &self,
+        columns: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+    ) -> Result<(), String> {
+        let mut in_domain = vec![true; columns[0].len()];
+        self.column_get_into(columns, bufs, &mut in_domain);
+        domain_error(self.out_of_domain(), &in_domain)
+    }
+
+    fn get_into(&self, pt: &[f64], buf: &mut [f64]) -> bool {
+        let in_domain = self.lookup.get_into(pt, buf);
+        if !in_domain && self.out_of_domain() == OutOfDomain::Identity {
+            buf.copy_from_slice(pt);
+        }
+        in_domain
+    }
+
+    fn bulk_get_into(&self, pts: &[&[f64]], bufs: &mut [&mut [f64]], in_domain: &mut [bool]) {
+        self.lookup.bulk_get_into(pts, bufs, in_domain);
+        if self.out_of_domain() == OutOfDomain::Identity {
+            for ((pt, buf), d) in pts.iter().zip(bufs.iter_mut()).zip(in_domain.iter()) {
+                if !d {
+                    buf.copy_from_slice(pt);
+                }
+            }
+        }
+    }
+
+    fn column_get_into(&self, columns: &[&[f64]], bufs: &mut [&mut [f64]], in_domain: &mut [bool]) {
+        self.lookup.column_get_into(columns, bufs, in_domain);
+        if self.out_of_domain() == OutOfDomain::Identity {
+            for (col, buf) in columns.iter().zip(bufs.iter_mut()) {
+                for ((b, c), d) in buf.iter_mut().zip(col.iter()).zip(in_domain.iter()) {
+                    if !d {
+                        *b = *c;
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl Transformation for Coordinate {
     fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
-        self.provider.get_into(pt, buf);
+        self.get_into(pt, buf);
     }
 
     fn bulk_transform_into(&self, pts: &[&[f64]], bufs: &mut [&mut [f64]]) {
-        self.provider.bulk_get_into(pts, bufs);
+        let mut in_domain = vec![true; pts.len()];
+        self.bulk_get_into(pts, bufs, &mut in_domain);
     }
 
     fn column_transform_into(&self, columns: &[&[f64]], bufs: &mut [&mut [f64]]) {
-        self.provider.column_get_into(columns, bufs);
+        let mut in_domain = vec![true; columns[0].len()];
+        self.column_get_into(columns, bufs, &mut in_domain);
     }
 
     fn invert(&self) -> Option<std::sync::Arc<dyn Transformation>> {
@@ -38,10 +151,104 @@ impl Transformation for Coordinate {
     }
 
     fn input_ndim(&self) -> usize {
-        self.provider.index_len()
+        self.lookup.input_ndim()
     }
 
     fn output_ndim(&self) -> usize {
-        self.provider.output_len()
+        self.lookup.output_ndim()
+    }
+
+    fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) {
+        if !self.lookup.jacobian_into(pt, buf) && self.out_of_domain() == OutOfDomain::Identity {
+            buf.fill(0.0);
+            let ndim = pt.len();
+            for idx in 0..ndim {
+                buf[idx * ndim + idx] = 1.0;
+            }
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.lookup.estimated_cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+    use crate::transforms::{Scale, SequenceBuilder, Translate};
+
+    /// Maps index `[i, j]` to `[i * j, i + 2j]`.
+    #[derive(Debug)]
+    struct Product;
+
+    impl ArrayProvider for Product {
+        fn get_into(&self, pt: &[f64], buf: &mut [f64]) {
+            buf[0] = pt[0] * pt[1];
+            buf[1] = pt[0] + 2.0 * pt[1];
+        }
+
+        fn index_len(&self) -> usize {
+            2
+        }
+
+        fn output_len(&self) -> usize {
+            2
+        }
+    }
+
+    fn field_transform() -> Arc<dyn Transformation> {
+        let mut builder = SequenceBuilder::default();
+        builder
+            .add_transform(Translate::try_new(&[-1.0, 2.0]).unwrap())
+            .unwrap()
+            .add_transform(Scale::try_new(&[0.5, 0.25]).unwrap())
+            .unwrap();
+        builder.build_any().unwrap()
+    }
+
+    #[test]
+    fn test_field_transform() {
+        let coord = Coordinate::new(Product)
+            .with_field_transform(field_transform())
+            .unwrap();
+        let mut buf = [f64::NAN; 2];
+        coord.transform_into(&[5.0, 6.0], &mut buf);
+        // index [2, 2]
+        assert_eq!(buf, [4.0, 6.0]);
+
+        let pts = [[5.0, 6.0], [1.0, -2.0]];
+        let mut cols = vec![vec![f64::NAN; 2]; 2];
+        coord.column_transform_into(
+            &[&[5.0, 1.0], &[6.0, -2.0]],
+            &mut cols
+                .iter_mut()
+                .map(|c| c.as_mut_slice())
+                .collect::<Vec<_>>(),
+        );
+        for (idx, pt) in pts.iter().enumerate() {
+            coord.transform_into(pt, &mut buf);
+            assert_eq!([cols[0][idx], cols[1][idx]], buf);
+        }
+    }
+
+    #[test]
+    fn test_field_transform_mismatch() {
+        let t = Arc::new(Scale::try_new(&[1.0, 2.0, 3.0]).unwrap());
+        assert!(Coordinate::new(Product).with_field_transform(t).is_err());
+    }
+
+    #[test]
+    fn test_jacobian() {
+        let coord = Coordinate::new(Product)
+            .with_field_transform(field_transform())
+            .unwrap();
+        let mut jac = [f64::NAN; 4];
+        // index [2, 2]; d(index)/d(pt) = diag(0.5, 0.25)
+        coord.jacobian_into(&[5.0, 6.0], &mut jac);
+        let expected = [2.0 * 0.5, 2.0 * 0.25, 0.5, 2.0 * 0.25];
+        assert_abs_diff_eq!(jac.as_slice(), expected.as_slice(), epsilon = 1e-6);
     }
 }
@@ -1,49 +1,284 @@
 use std::sync::Arc;
 
-use crate::{Transformation, traits::ArrayProvider};
+use crate::{
+    Transformation, as_muts, as_refs,
+    traits::ArrayProvider,
+    transforms::{
+        OutOfDomain,
+        field::{FieldLookup, domain_error},
+    },
+    vec_of_vec,
+};
 
-#[derive(Debug)]
+/// Maximum number of fixed-point iterations when inverting a displacement.
+const MAX_ITERATIONS: usize = 100;
+
+/// Inverting a displacement stops when no coordinate changes by more than this.
+const TOLERANCE: f64 = 1e-10;
+
+/// Add a displacement, looked up in a displacement field, to each input point.
+#[derive(Debug, Clone)]
 pub struct Displacement {
-    provider: Arc<dyn ArrayProvider>,
+    lookup: FieldLookup,
 }
 
 impl Displacement {
     pub fn new_any(provider: Arc<dyn ArrayProvider>) -> Self {
-        Self { provider }
+        Self {
+            lookup: FieldLookup::new(provider),
+        }
     }
+
     pub fn new<P: ArrayProvider + 'static>(provider: P) -> Self {
         Self::new_any(Arc::new(provider))
     }
+
+    /// Map input points into the field's index space before lookup,
+    /// e.g. with the inverse of the field grid's scale and translation.
+    /// The displacement is still added to the input point.
+    ///
+    /// Fails if the transform's output does not match the field's index dimensionality,
+    /// or its input does not match the displacement vectors'.
+    pub fn with_field_transform(
+        self,
+        field_transform: Arc<dyn Transformation>,
+    ) -> Result<Self, String> {
+        if field_transform.input_ndim() != self.lookup.output_ndim() {
+            return Err(
+                "Field transform input dimensionality does not match displacement dimensionality"
+                    .into(),
+            );
+        }
+        Ok(Self {
+            lookup: self.lookup.try_with_field_transform(field_transform)?,
+        })
+    }
+
+    /// The transform from input points into the field's index space, if it is not the identity.
+    pub fn field_transform(&self) -> Option<&Arc<dyn Transformation>> {
+        self.lookup.field_transform()
+    }
+
+    /// Set what happens to points outside the field's domain (by default, [OutOfDomain::Provider]).
+    pub fn with_out_of_domain(self, out_of_domain: OutOfDomain) -> Self {
+        Self {
+            lookup: self.lookup.with_out_of_domain(out_of_domain),
+        }
+    }
+
+    pub fn out_of_domain(&self) -> OutOfDomain {
+        self.lookup.out_of_domain()
+    }
+
+    /// As [Transformation::transform_into],
+    /// but fails if the point is outside the field's domain and the policy is [OutOfDomain::Error].
+    pub fn try_transform_into(&self, pt: &[f64], buf: &mut [f64]) -> Result<(), String> {
+        let in_domain = self.displacement_into(pt, buf);
+        add_point(pt, buf);
+        domain_error(self.out_of_domain(), &[in_domain])
+    }
+
+    /// As [Transformation::bulk_transform_into],
+    /// but fails if any point is outside the field's domain and the policy is [OutOfDomain::Error].
+    pub fn try_bulk_transform_into(
+        &self,
+        pts: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+    ) -> Result<(), String> {
+        let mut in_domain = vec![true; pts.len()];
+        self.bulk_displacement_into(pts, bufs, &mut in_domain);
+        for (pt, buf) in pts.iter().zip(bufs.iter_mut()) {
+            add_point(pt, buf);
+        }
+        domain_error(self.out_of_domain(), &in_domain)
+    }
+
+    /// As [Transformation::column_transform_into],
+    /// but fails if any point is outside the field's domain and the policy is [OutOfDomain::Error].
+    pub fn try_column_transform_into(
+        &self,
+        columns: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+    ) -> Result<(), String> {
+        let mut in_domain = vec![true; columns[0].len()];
+        self.column_displacement_into(columns, bufs, &mut in_domain);
+        for (col, buf) in columns.iter().zip(bufs.iter_mut()) {
+            add_point(col, buf);
+        }
+        domain_error(self.out_of_domain(), &in_domain)
+    }
+
+    /// The displacement at a point, returning whether it is in the field's domain.
+    fn displacement_into(&self, pt: &[f64], buf: &mut [f64]) -> bool {
+        let in_domain = self.lookup.get_into(pt, buf);
+        if !in_domain && self.out_of_domain() == OutOfDomain::Identity {
+            buf.fill(0.0);
+        }
+        in_domain
+    }
+
+    fn bulk_displacement_into(
+        &self,
+        pts: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+        in_domain: &mut [bool],
+    ) {
+        self.lookup.bulk_get_into(pts, bufs, in_domain);
+        if self.out_of_domain() == OutOfDomain::Identity {
+            for (buf, d) in bufs.iter_mut().zip(in_domain.iter()) {
+                if !d {
+                    buf.fill(0.0);
+                }
+            }
+        }
+    }
+
+    fn column_displacement_into(
+        &self,
+        columns: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+        in_domain: &mut [bool],
+    ) {
+        self.lookup.column_get_into(columns, bufs, in_domain);
+        if self.out_of_domain() == OutOfDomain::Identity {
+            for buf in bufs.iter_mut() {
+                for (b, d) in buf.iter_mut().zip(in_domain.iter()) {
+                    if !d {
+                        *b = 0.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Add the input point (or column) to its displacement.
+fn add_point(pt: &[f64], buf: &mut [f64]) {
+    for (i, o) in pt.iter().zip(buf.iter_mut()) {
+        *o += i;
+    }
 }
 
 impl Transformation for Displacement {
     fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
-        self.provider.get_into(pt, buf);
-        for (i, o) in pt.iter().zip(buf.iter_mut()) {
-            *o += i;
-        }
+        self.displacement_into(pt, buf);
+        add_point(pt, buf);
     }
 
     fn bulk_transform_into(&self, pts: &[&[f64]], bufs: &mut [&mut [f64]]) {
-        self.provider.bulk_get_into(pts, bufs);
+        let mut in_domain = vec![true; pts.len()];
+        self.bulk_displacement_into(pts, bufs, &mut in_domain);
         for (pt, buf) in pts.iter().zip(bufs.iter_mut()) {
-            for (i, o) in pt.iter().zip(buf.iter_mut()) {
-                *o += i;
+            add_point(pt, buf);
+        }
+    }
+
+    fn column_transform_into(&self, columns: &[&[f64]], bufs: &mut [&mut [f64]]) {
+        let mut in_domain = vec![true; columns[0].len()];
+        self.column_displacement_into(columns, bufs, &mut in_domain);
+        for (col, buf) in columns.iter().zip(bufs.iter_mut()) {
+            add_point(col, buf);
+        }
+    }
+
+    /// Numerically, by fixed-point iteration;
+    /// this converges where the displacement field is smooth enough to be invertible.
+    /// Points where it does not converge are transformed to NaN.
+    fn invert(&self) -> Option<std::sync::Arc<dyn Transformation>> {
+        Some(Arc::new(InverseDisplacement(self.clone())))
+    }
+
+    fn is_identity(&self) -> bool {
+        false
+    }
+
+    fn input_ndim(&self) -> usize {
+        self.lookup.input_ndim()
+    }
+
+    fn output_ndim(&self) -> usize {
+        self.lookup.output_ndim()
+    }
+
+    fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) {
+        if !self.lookup.jacobian_into(pt, buf) && self.out_of_domain() == OutOfDomain::Identity {
+            buf.fill(0.0);
+        }
+        let ndim = pt.len();
+        for idx in 0..ndim {
+            buf[idx * ndim + idx] += 1.0;
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        // lookup, then add to the input
+        self.lookup.estimated_cost() + self.output_ndim() as f64
+    }
+}
+
+/// The inverse of a [Displacement], found by fixed-point iteration:
+/// the `x` satisfying `y = x + d(x)` is the limit of `x <- y - d(x)`.
+#[derive(Debug)]
+struct InverseDisplacement(Displacement);
+
+impl Transformation for InverseDisplacement {
+    fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
+        let mut disp = vec![f64::NAN; pt.len()];
+        buf.copy_from_slice(pt);
+        for _ in 0..MAX_ITERATIONS {
+            self.0.displacement_into(buf, &mut disp);
+            let mut change: f64 = 0.0;
+            for ((b, p), d) in buf.iter_mut().zip(pt).zip(disp.iter()) {
+                let next = p - d;
+                change = change.max((next - *b).abs());
+                *b = next;
+            }
+            if change <= TOLERANCE {
+                return;
             }
         }
+        buf.fill(f64::NAN);
     }
 
     fn column_transform_into(&self, columns: &[&[f64]], bufs: &mut [&mut [f64]]) {
-        self.provider.column_get_into(columns, bufs);
-        for (col, out_col) in columns.iter().zip(bufs.iter_mut()) {
-            for (i, o) in col.iter().zip(out_col.iter_mut()) {
-                *o += i;
+        let n_pts = columns[0].len();
+        for (col, buf) in columns.iter().zip(bufs.iter_mut()) {
+            buf.copy_from_slice(col);
+        }
+        let mut disp = vec_of_vec(columns.len(), n_pts, f64::NAN);
+        let mut converged = vec![false; n_pts];
+        let mut in_domain = vec![true; n_pts];
+        for _ in 0..MAX_ITERATIONS {
+            self.0.column_displacement_into(
+                &as_refs(bufs),
+                &mut as_muts(&mut disp),
+                &mut in_domain,
+            );
+            converged.fill(true);
+            for ((col, buf), d) in columns.iter().zip(bufs.iter_mut()).zip(disp.iter()) {
+                for (idx, conv) in converged.iter_mut().enumerate() {
+                    let next = col[idx] - d[idx];
+                    if (next - buf[idx]).abs() > TOLERANCE {
+                        *conv = false;
+                    }
+                    buf[idx] = next;
+                }
+            }
+            if converged.iter().all(|c| *c) {
+                return;
+            }
+        }
+        for buf in bufs.iter_mut() {
+            for (b, conv) in buf.iter_mut().zip(converged.iter()) {
+                if !conv {
+                    *b = f64::NAN;
+                }
             }
         }
     }
 
-    fn invert(&self) -> Option<std::sync::Arc<dyn Transformation>> {
-        None
+    fn invert(&self) -> Option<Arc<dyn Transformation>> {
+        Some(Arc::new(self.0.clone()))
     }
 
     fn is_identity(&self) -> bool {
@@ -51,10 +286,231 @@ impl Transformation for Displacement {
     }
 
     fn input_ndim(&self) -> usize {
-        self.provider.index_len()
+        self.0.output_ndim()
     }
 
     fn output_ndim(&self) -> usize {
-        self.provider.output_len()
+        self.0.input_ndim()
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        // a handful of iterations is typical
+        5.0 * self.0.estimated_cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+    use crate::transforms::Scale;
+
+    /// A smooth displacement `0.2 * [sin(j), cos(i)]` at index `[i, j]`.
+    #[derive(Debug)]
+    struct Wavy;
+
+    impl ArrayProvider for Wavy {
+        fn get_into(&self, pt: &[f64], buf: &mut [f64]) {
+            buf[0] = 0.2 * pt[1].sin();
+            buf[1] = 0.2 * pt[0].cos();
+        }
+
+        fn index_len(&self) -> usize {
+            2
+        }
+
+        fn output_len(&self) -> usize {
+            2
+        }
+    }
+
+    /// A constant displacement of `[1, -1]` defined on a 4x4 grid.
+    #[derive(Debug)]
+    struct Bounded;
+
+    impl ArrayProvider for Bounded {
+        fn get_into(&self, _pt: &[f64], buf: &mut [f64]) {
+            buf.copy_from_slice(&[1.0, -1.0]);
+        }
+
+        fn index_len(&self) -> usize {
+            2
+        }
+
+        fn output_len(&self) -> usize {
+            2
+        }
+
+        fn domain(&self) -> Option<&[usize]> {
+            Some(&[4, 4])
+        }
+    }
+
+    /// A displacement of `[i / 2, 0]` at index `[i, j]`, defined on a 4x4 grid.
+    #[derive(Debug)]
+    struct Ramp;
+
+    impl ArrayProvider for Ramp {
+        fn get_into(&self, pt: &[f64], buf: &mut [f64]) {
+            buf.copy_from_slice(&[pt[0] / 2.0, 0.0]);
+        }
+
+        fn index_len(&self) -> usize {
+            2
+        }
+
+        fn output_len(&self) -> usize {
+            2
+        }
+
+        fn domain(&self) -> Option<&[usize]> {
+            Some(&[4, 4])
+        }
+    }
+
+    fn make_transform() -> Displacement {
+        Displacement::new(Wavy)
+            .with_field_transform(Arc::new(Scale::try_new(&[0.5, 2.0]).unwrap()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_field_transform() {
+        let t = make_transform();
+        let mut buf = [f64::NAN; 2];
+        t.transform_into(&[1.0, 0.5], &mut buf);
+        let expected = [1.0 + 0.2 * 1.0f64.sin(), 0.5 + 0.2 * 0.5f64.cos()];
+        assert_abs_diff_eq!(buf.as_slice(), expected.as_slice(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let t = make_transform();
+        let inv = t.invert().unwrap();
+        let pts = [[1.0, 0.5], [-3.0, 2.0], [10.0, 7.5]];
+        let mut fwd = [f64::NAN; 2];
+        let mut back = [f64::NAN; 2];
+        for pt in pts.iter() {
+            t.transform_into(pt, &mut fwd);
+            inv.transform_into(&fwd, &mut back);
+            assert_abs_diff_eq!(back.as_slice(), pt.as_slice(), epsilon = 1e-8);
+        }
+
+        let fwd_cols: Vec<Vec<f64>> = (0..2)
+            .map(|d| {
+                pts.iter()
+                    .map(|pt| {
+                        t.transform_into(pt, &mut fwd);
+                        fwd[d]
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut back_cols = vec_of_vec(2, pts.len(), f64::NAN);
+        inv.column_transform_into(&as_refs(&fwd_cols), &mut as_muts(&mut back_cols));
+        for (idx, pt) in pts.iter().enumerate() {
+            assert_abs_diff_eq!(back_cols[0][idx], pt[0], epsilon = 1e-8);
+            assert_abs_diff_eq!(back_cols[1][idx], pt[1], epsilon = 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_jacobian() {
+        let t = make_transform();
+        let mut jac = [f64::NAN; 4];
+        t.jacobian_into(&[1.0, 0.5], &mut jac);
+        // index [0.5, 1.0]
+        let expected = [
+            1.0,
+            0.2 * 1.0f64.cos() * 2.0,
+            -0.2 * 0.5f64.sin() * 0.5,
+            1.0,
+        ];
+        assert_abs_diff_eq!(jac.as_slice(), expected.as_slice(), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_out_of_domain() {
+        let pts = [[1.0, 2.0], [5.0, 2.0]];
+        let transform_pts = |t: &Displacement| {
+            pts.map(|pt| {
+                let mut buf = [f64::NAN; 2];
+                t.transform_into(&pt, &mut buf);
+                buf
+            })
+        };
+
+        let t = Displacement::new(Bounded);
+        assert_eq!(t.out_of_domain(), OutOfDomain::Provider);
+        let out = transform_pts(&t);
+        assert_eq!(out, [[2.0, 1.0], [6.0, 1.0]]);
+
+        let out = transform_pts(&t.clone().with_out_of_domain(OutOfDomain::Nan));
+        assert_eq!(out[0], [2.0, 1.0]);
+        assert!(out[1].iter().all(|v| v.is_nan()));
+
+        let out = transform_pts(&t.clone().with_out_of_domain(OutOfDomain::Identity));
+        assert_eq!(out, [[2.0, 1.0], [5.0, 2.0]]);
+
+        let out = transform_pts(&t.clone().with_out_of_domain(OutOfDomain::Clamp));
+        assert_eq!(out, [[2.0, 1.0], [6.0, 1.0]]);
+
+        let t = t.with_out_of_domain(OutOfDomain::Error);
+        let mut buf = [f64::NAN; 2];
+        assert!(t.try_transform_into(&pts[0], &mut buf).is_ok());
+        assert!(t.try_transform_into(&pts[1], &mut buf).is_err());
+        let columns = [vec![1.0, 5.0], vec![2.0, 2.0]];
+        let mut out = vec_of_vec(2, 2, f64::NAN);
+        assert!(
+            t.try_column_transform_into(&as_refs(&columns), &mut as_muts(&mut out))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_jacobian_out_of_domain() {
+        let jacobian = |out_of_domain, pt: &[f64]| {
+            let mut jac = [f64::NAN; 4];
+            Displacement::new(Ramp)
+                .with_out_of_domain(out_of_domain)
+                .jacobian_into(pt, &mut jac);
+            jac
+        };
+        let inside = [1.5, 0.0, 0.0, 1.0];
+        for ood in [
+            OutOfDomain::Provider,
+            OutOfDomain::Nan,
+            OutOfDomain::Identity,
+            OutOfDomain::Clamp,
+            OutOfDomain::Error,
+        ] {
+            assert_abs_diff_eq!(
+                jacobian(ood, &[1.0, 1.0]).as_slice(),
+                inside.as_slice(),
+                epsilon = 1e-6
+            );
+        }
+
+        let outside = [5.0, 1.0];
+        assert_abs_diff_eq!(
+            jacobian(OutOfDomain::Provider, &outside).as_slice(),
+            inside.as_slice(),
+            epsilon = 1e-6
+        );
+        assert!(
+            jacobian(OutOfDomain::Nan, &outside)
+                .iter()
+                .all(|j| j.is_nan())
+        );
+        assert!(
+            jacobian(OutOfDomain::Error, &outside)
+                .iter()
+                .all(|j| j.is_nan())
+        );
+        // zero displacement, and clamped along the first axis
+        let identity = [1.0, 0.0, 0.0, 1.0];
+        assert_eq!(jacobian(OutOfDomain::Identity, &outside), identity);
+        assert_eq!(jacobian(OutOfDomain::Clamp, &outside), identity);
     }
 }
@@ -0,0 +1,355 @@
+use std::sync::Arc;
+
+use crate::{
+    Transformation, as_muts, as_refs,
+    traits::{ArrayProvider, finite_difference},
+    vec_of_vec,
+};
+
+/// What a [Coordinate](super::Coordinate) or [Displacement](super::Displacement) transform
+/// does with points outside of its field's [domain](ArrayProvider::domain).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfDomain {
+    /// Look up the field anyway, leaving points outside the domain
+    /// to the provider's own boundary handling
+    /// (e.g. an [ArrayField](crate::indexer::ArrayField)'s [Boundary](crate::indexer::Boundary)).
+    #[default]
+    Provider,
+    /// Output NaN.
+    Nan,
+    /// Output the input point, i.e. zero displacement.
+    Identity,
+    /// Use the nearest value within the domain.
+    Clamp,
+    /// Output NaN, and report an error from the checked transform methods.
+    Error,
+}
+
+/// An [ArrayProvider] looked up at input points,
+/// optionally mapped into the provider's index space by a field transform first.
+#[derive(Debug, Clone)]
+pub(crate) struct FieldLookup {
+    provider: Arc<dyn ArrayProvider>,
+    field_transform: Option<Arc<dyn Transformation>>,
+    out_of_domain: OutOfDomain,
+}
+
+impl FieldLookup {
+    pub fn new(provider: Arc<dyn ArrayProvider>) -> Self {
+        Self {
+            provider,
+            field_transform: None,
+            out_of_domain: Default::default(),
+        }
+    }
+
+    pub fn try_with_field_transform(
+        mut self,
+        field_transform: Arc<dyn Transformation>,
+    ) -> Result<Self, String> {
+        if field_transform.output_ndim() != self.provider.index_len() {
+            return Err(
+                "Field transform output dimensionality does not match field index dimensionality"
+                    .into(),
+            );
+        }
+        self.field_transform = (!field_transform.is_identity()).then_some(field_transform);
+        Ok(self)
+    }
+
+    pub fn field_transform(&self) -> Option<&Arc<dyn Transformation>> {
+        self.field_transform.as_ref()
+    }
+
+    pub fn with_out_of_domain(mut self, out_of_domain: OutOfDomain) -> Self {
+        self.out_of_domain = out_of_domain;
+        self
+    }
+
+    pub fn out_of_domain(&self) -> OutOfDomain {
+        self.out_of_domain
+    }
+
+    pub fn input_ndim(&self) -> usize {
+        match &self.field_transform {
+            Some(t) => t.input_ndim(),
+            None => self.provider.index_len(),
+        }
+    }
+
+    pub fn output_ndim(&self) -> usize {
+        self.provider.output_len()
+    }
+
+    fn index_ndim(&self) -> usize {
+        self.provider.index_len()
+    }
+
+    /// Whether out-of-domain indices are clamped into the domain, so must be modified before lookup.
+    fn clamps(&self) -> bool {
+        self.out_of_domain == OutOfDomain::Clamp && self.provider.domain().is_some()
+    }
+
+    /// Whether an index is within the provider's domain, or the policy is to look it up regardless.
+    fn in_domain(&self, idx: &[f64]) -> bool {
+        if idx.iter().any(|i| i.is_nan()) {
+            return false;
+        }
+        if self.out_of_domain == OutOfDomain::Provider {
+            return true;
+        }
+        let Some(domain) = self.provider.domain() else {
+            return true;
+        };
+        idx.iter()
+            .zip(domain)
+            .all(|(i, extent)| *i >= 0.0 && *i <= extent.saturating_sub(1) as f64)
+    }
+
+    /// Whether an index is within the provider's domain,
+    /// after clamping it into the domain if that is the policy.
+    fn resolve(&self, idx: &mut [f64]) -> bool {
+        if self.clamps()
+            && !idx.iter().any(|i| i.is_nan())
+            && let Some(domain) = self.provider.domain()
+        {
+            for (i, extent) in idx.iter_mut().zip(domain) {
+                *i = i.clamp(0.0, extent.saturating_sub(1) as f64);
+            }
+        }
+        self.in_domain(idx)
+    }
+
+    /// The index for a point, or [None] if it can be used directly.
+    fn index(&self, pt: &[f64]) -> Option<Vec<f64>> {
+        match &self.field_transform {
+            Some(t) => {
+                let mut idx = vec![f64::NAN; t.output_ndim()];
+                t.transform_into(pt, &mut idx);
+                Some(idx)
+            }
+            None => self.clamps().then(|| pt.to_vec()),
+        }
+    }
+
+    /// Look up the value for a point, returning whether it is in the domain.
+    /// Points out of the domain are NaN.
+    pub fn get_into(&self, pt: &[f64], buf: &mut [f64]) -> bool {
+        let mut owned = self.index(pt);
+        let in_domain = match owned.as_mut() {
+            Some(idx) => self.resolve(idx),
+            None => self.in_domain(pt),
+        };
+        if !in_domain {
+            buf.fill(f64::NAN);
+            return false;
+        }
+        self.provider.get_into(owned.as_deref().unwrap_or(pt), buf);
+        true
+    }
+
+    /// Look up the values for points, writing whether each is in the domain into `in_domain`.
+    /// Points out of the domain are NaN, and are not looked up.
+    pub fn bulk_get_into(&self, pts: &[&[f64]], bufs: &mut [&mut [f64]], in_domain: &mut [bool]) {
+        let mut owned = match &self.field_transform {
+            Some(t) => {
+                let mut idxs = vec_of_vec(pts.len(), t.output_ndim(), f64::NAN);
+                t.bulk_transform_into(pts, &mut as_muts(&mut idxs));
+                Some(idxs)
+            }
+            None => self
+                .clamps()
+                .then(|| pts.iter().map(|p| p.to_vec()).collect()),
+        };
+        match owned.as_mut() {
+            Some(idxs) => {
+                for (idx, d) in idxs.iter_mut().zip(in_domain.iter_mut()) {
+                    *d = self.resolve(idx);
+                }
+            }
+            None => {
+                for (pt, d) in pts.iter().zip(in_domain.iter_mut()) {
+                    *d = self.in_domain(pt);
+                }
+            }
+        }
+        let idxs = match &owned {
+            Some(idxs) => as_refs(idxs),
+            None => pts.to_vec(),
+        };
+
+        if in_domain.iter().all(|d| *d) {
+            self.provider.bulk_get_into(&idxs, bufs);
+            return;
+        }
+        let valid: Vec<&[f64]> = idxs
+            .iter()
+            .zip(in_domain.iter())
+            .filter_map(|(idx, d)| d.then_some(*idx))
+            .collect();
+        let mut valid_bufs: Vec<&mut [f64]> = vec![];
+        for (buf, d) in bufs.iter_mut().zip(in_domain.iter()) {
+            if *d {
+                valid_bufs.push(buf);
+            } else {
+                buf.fill(f64::NAN);
+            }
+        }
+        if !valid.is_empty() {
+            self.provider.bulk_get_into(&valid, &mut valid_bufs);
+        }
+    }
+
+    /// Look up the values for columns of points, writing whether each is in the domain into `in_domain`.
+    /// Points out of the domain are NaN, and are not looked up.
+    pub fn column_get_into(
+        &self,
+        columns: &[&[f64]],
+        bufs: &mut [&mut [f64]],
+        in_domain: &mut [bool],
+    ) {
+        let n_pts = columns[0].len();
+        let mut owned = match &self.field_transform {
+            Some(t) => {
+                let mut idx_cols = vec_of_vec(t.output_ndim(), n_pts, f64::NAN);
+                t.column_transform_into(columns, &mut as_muts(&mut idx_cols));
+                Some(idx_cols)
+            }
+            None => self
+                .clamps()
+                .then(|| columns.iter().map(|c| c.to_vec()).collect()),
+        };
+        let mut idx = vec![f64::NAN; self.index_ndim()];
+        for (pt_idx, d) in in_domain.iter_mut().enumerate() {
+            match owned.as_mut() {
+                Some(idx_cols) => {
+                    idx.iter_mut()
+                        .zip(idx_cols.iter())
+                        .for_each(|(i, col)| *i = col[pt_idx]);
+                    *d = self.resolve(&mut idx);
+                    // write back any clamping
+                    idx_cols
+                        .iter_mut()
+                        .zip(idx.iter())
+                        .for_each(|(col, i)| col[pt_idx] = *i);
+                }
+                None => {
+                    idx.iter_mut()
+                        .zip(columns.iter())
+                        .for_each(|(i, col)| *i = col[pt_idx]);
+                    *d = self.in_domain(&idx);
+                }
+            }
+        }
+        let idx_cols = match &owned {
+            Some(idx_cols) => as_refs(idx_cols),
+            None => columns.to_vec(),
+        };
+
+        let n_valid = in_domain.iter().filter(|d| **d).count();
+        if n_valid == n_pts {
+            self.provider.column_get_into(&idx_cols, bufs);
+            return;
+        }
+        for buf in bufs.iter_mut() {
+            buf.fill(f64::NAN);
+        }
+        if n_valid == 0 {
+            return;
+        }
+        let valid_cols: Vec<Vec<f64>> = idx_cols
+            .iter()
+            .map(|col| {
+                col.iter()
+                    .zip(in_domain.iter())
+                    .filter_map(|(c, d)| d.then_some(*c))
+                    .collect()
+            })
+            .collect();
+        let mut valid_bufs = vec_of_vec(bufs.len(), n_valid, f64::NAN);
+        self.provider
+            .column_get_into(&as_refs(&valid_cols), &mut as_muts(&mut valid_bufs));
+        for (buf, valid_buf) in bufs.iter_mut().zip(valid_bufs.iter()) {
+            let mut values = valid_buf.iter();
+            for (b, d) in buf.iter_mut().zip(in_domain.iter()) {
+                if *d {
+                    *b = *values.next().expect("one value per valid point");
+                }
+            }
+        }
+    }
+
+    /// The Jacobian of the looked-up values with respect to the input point, row-major,
+    /// returning whether the point is in the domain.
+    ///
+    /// The provider's is estimated by finite differences in index space,
+    /// and chained with the field transform's.
+    /// Points out of the domain are NaN, except under [OutOfDomain::Clamp],
+    /// where the values do not vary along axes which are clamped.
+    pub fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) -> bool {
+        let out_ndim = self.output_ndim();
+        let idx_ndim = self.index_ndim();
+        let unclamped = match &self.field_transform {
+            Some(t) => {
+                let mut idx = vec![f64::NAN; idx_ndim];
+                t.transform_into(pt, &mut idx);
+                idx
+            }
+            None => pt.to_vec(),
+        };
+        let mut idx = unclamped.clone();
+        if !self.resolve(&mut idx) {
+            buf.fill(f64::NAN);
+            return false;
+        }
+
+        let lookup = |p: &[f64], b: &mut [f64]| self.provider.get_into(p, b);
+        let mut provider_jac = vec![f64::NAN; out_ndim * idx_ndim];
+        finite_difference(lookup, out_ndim, &idx, &mut provider_jac);
+        for (k, (i, u)) in idx.iter().zip(unclamped.iter()).enumerate() {
+            if i != u {
+                provider_jac
+                    .iter_mut()
+                    .skip(k)
+                    .step_by(idx_ndim)
+                    .for_each(|j| *j = 0.0);
+            }
+        }
+        let Some(t) = &self.field_transform else {
+            buf.copy_from_slice(&provider_jac);
+            return true;
+        };
+
+        let in_ndim = t.input_ndim();
+        let mut field_jac = vec![f64::NAN; idx_ndim * in_ndim];
+        t.jacobian_into(pt, &mut field_jac);
+
+        for row in 0..out_ndim {
+            for col in 0..in_ndim {
+                buf[row * in_ndim + col] = (0..idx_ndim)
+                    .map(|k| provider_jac[row * idx_ndim + k] * field_jac[k * in_ndim + col])
+                    .sum();
+            }
+        }
+        true
+    }
+
+    pub fn estimated_cost(&self) -> f64 {
+        self.provider.estimated_cost()
+            + self
+                .field_transform
+                .as_ref()
+                .map_or(0.0, |t| t.estimated_cost())
+    }
+}
+
+/// The error for the first point out of the domain, if the policy is to report errors.
+pub(crate) fn domain_error(out_of_domain: OutOfDomain, in_domain: &[bool]) -> Result<(), String> {
+    if out_of_domain != OutOfDomain::Error {
+        return Ok(());
+    }
+    match in_domain.iter().position(|d| !d) {
+        Some(idx) => Err(format!("Point {idx} is outside the field's domain")),
+        None => Ok(()),
+    }
+}
@@ -27,6 +27,18 @@ impl Transformation for Identity {
         self.0
     }
 
+    fn jacobian_into(&self, _pt: &[f64], buf: &mut [f64]) {
+        let ndim = self.0;
+        buf.fill(0.0);
+        for idx in 0..ndim {
+            buf[idx * ndim + idx] = 1.0;
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        0.0
+    }
+
     fn transform_into(&self, pt: &[f64], buf: &mut [f64]) {
         buf.copy_from_slice(pt);
     }
@@ -27,6 +27,12 @@ impl MapAxis {
 
         Ok(Self(map.to_smallvec()))
     }
+
+    /// For an input point `p` and this vector `m`,
+    /// index `i` in the output point is given by `p[m[i]]`.
+    pub fn map(&self) -> &[usize] {
+        &self.0
+    }
 }
 
 impl Transformation for MapAxis {
@@ -58,6 +64,18 @@ impl Transformation for MapAxis {
         self.0.len()
     }
 
+    fn jacobian_into(&self, _pt: &[f64], buf: &mut [f64]) {
+        let ndim = self.0.len();
+        buf.fill(0.0);
+        for (out_idx, in_idx) in self.0.iter().enumerate() {
+            buf[out_idx * ndim + in_idx] = 1.0;
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.0.len() as f64
+    }
+
     fn is_identity(&self) -> bool {
         self.0.iter().enumerate().all(|(a, b)| a == *b)
     }
@@ -68,7 +86,7 @@ mod tests {
     use super::MapAxis;
     use crate::tests::{
         check_inverse_transform_bulk, check_inverse_transform_col, check_inverse_transform_coord,
-        check_transform_bulk, check_transform_col,
+        check_jacobian, check_transform_bulk, check_transform_col,
     };
 
     fn make_transform() -> MapAxis {
@@ -98,4 +116,9 @@ mod tests {
     fn test_inverse_columns() {
         check_inverse_transform_col(make_transform());
     }
+
+    #[test]
+    fn test_jacobian() {
+        check_jacobian(make_transform());
+    }
 }
@@ -14,9 +14,15 @@ mod scale;
 pub use scale::Scale;
 mod sequence;
 pub use sequence::{Sequence, SequenceBuilder};
+mod simplify;
+pub use simplify::simplify;
+mod compare;
+pub use compare::{approx_eq, disagreement, structural_hash};
 mod translate;
 pub use translate::Translate;
 mod coordinate;
+mod field;
 pub use coordinate::Coordinate;
+pub use field::OutOfDomain;
 mod displacement;
 pub use displacement::Displacement;
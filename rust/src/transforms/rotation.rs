@@ -26,6 +26,10 @@ impl Rotation {
         }
         Ok(Self { matrix })
     }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
 }
 
 impl Transformation for Rotation {
@@ -51,6 +55,18 @@ impl Transformation for Rotation {
         self.matrix.nrows()
     }
 
+    fn jacobian_into(&self, _pt: &[f64], buf: &mut [f64]) {
+        let ncols = self.matrix.ncols();
+        for (idx, b) in buf.iter_mut().enumerate() {
+            *b = self.matrix[(idx / ncols, idx % ncols)];
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        let n = self.matrix.nrows() as f64;
+        2.0 * n * n
+    }
+
     fn is_identity(&self) -> bool {
         self.matrix.is_identity()
     }
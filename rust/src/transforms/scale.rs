@@ -29,6 +29,11 @@ impl Scale {
         }
         Ok(Self(scale.to_smallvec()))
     }
+
+    /// The factor applied to each dimension.
+    pub fn factors(&self) -> &[f64] {
+        &self.0
+    }
 }
 
 impl Transformation for Scale {
@@ -58,6 +63,18 @@ impl Transformation for Scale {
         self.0.len()
     }
 
+    fn jacobian_into(&self, _pt: &[f64], buf: &mut [f64]) {
+        let ndim = self.0.len();
+        buf.fill(0.0);
+        for (idx, s) in self.0.iter().enumerate() {
+            buf[idx * ndim + idx] = *s;
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.0.len() as f64
+    }
+
     fn is_identity(&self) -> bool {
         self.0.iter().all(|s| *s == 1.0)
     }
@@ -68,7 +85,7 @@ mod tests {
     use super::Scale;
     use crate::tests::{
         check_inverse_transform_bulk, check_inverse_transform_col, check_inverse_transform_coord,
-        check_transform_bulk, check_transform_col,
+        check_jacobian, check_transform_bulk, check_transform_col,
     };
 
     fn make_transform() -> Scale {
@@ -99,4 +116,9 @@ mod tests {
     fn test_inverse_columns() {
         check_inverse_transform_col(make_transform());
     }
+
+    #[test]
+    fn test_jacobian() {
+        check_jacobian(make_transform());
+    }
 }
@@ -32,6 +32,11 @@ impl Sequence {
         SequenceBuilder(vec![])
     }
 
+    /// The transformations in the order they are applied.
+    pub fn transforms(&self) -> &[Arc<dyn Transformation>] {
+        &self.transforms
+    }
+
     fn transform_into_inner(
         &self,
         pt: &[f64],
@@ -135,6 +140,47 @@ impl Transformation for Sequence {
         self.transforms.last().unwrap().output_ndim()
     }
 
+    /// Chains the Jacobians of each transformation at its intermediate point.
+    fn jacobian_into(&self, pt: &[f64], buf: &mut [f64]) {
+        let in_ndim = self.input_ndim();
+        let mut point = pt.to_vec();
+        let mut next_point = vec![];
+        // Jacobian of the transformations so far, row-major
+        let mut total = vec![0.0; in_ndim * in_ndim];
+        for idx in 0..in_ndim {
+            total[idx * in_ndim + idx] = 1.0;
+        }
+        let mut step = vec![];
+        let mut product = vec![];
+        for t in self.transforms.iter() {
+            let (t_in, t_out) = (t.input_ndim(), t.output_ndim());
+            step.clear();
+            step.resize(t_out * t_in, 0.0);
+            t.jacobian_into(&point, &mut step);
+
+            product.clear();
+            product.resize(t_out * in_ndim, 0.0);
+            for row in 0..t_out {
+                for col in 0..in_ndim {
+                    product[row * in_ndim + col] = (0..t_in)
+                        .map(|k| step[row * t_in + k] * total[k * in_ndim + col])
+                        .sum();
+                }
+            }
+            std::mem::swap(&mut total, &mut product);
+
+            next_point.clear();
+            next_point.resize(t_out, f64::NAN);
+            t.transform_into(&point, &mut next_point);
+            std::mem::swap(&mut point, &mut next_point);
+        }
+        buf.copy_from_slice(&total);
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.transforms.iter().map(|t| t.estimated_cost()).sum()
+    }
+
     fn is_identity(&self) -> bool {
         self.transforms.iter().all(|t| t.is_identity())
     }
@@ -199,7 +245,7 @@ mod tests {
     use super::Sequence;
     use crate::tests::{
         check_inverse_transform_bulk, check_inverse_transform_col, check_inverse_transform_coord,
-        check_transform_bulk, check_transform_col,
+        check_jacobian, check_transform_bulk, check_transform_col,
     };
     use crate::transforms::{Scale, Translate};
 
@@ -235,4 +281,24 @@ mod tests {
     fn test_inverse_columns() {
         check_inverse_transform_col(make_transform());
     }
+
+    #[test]
+    fn test_estimated_cost() {
+        use crate::Transformation;
+
+        let t = make_transform();
+        assert_eq!(t.estimated_cost(), 6.0);
+        assert_eq!(
+            t.estimated_cost(),
+            t.transforms()
+                .iter()
+                .map(|t| t.estimated_cost())
+                .sum::<f64>()
+        );
+    }
+
+    #[test]
+    fn test_jacobian() {
+        check_jacobian(make_transform());
+    }
 }
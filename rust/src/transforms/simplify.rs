@@ -0,0 +1,401 @@
+use std::sync::Arc;
+
+use smallvec::smallvec;
+
+use crate::{
+    ShortVec, Transformation,
+    transforms::{
+        Affine, Bijection, ByDimension, Identity, MapAxis, Scale, Sequence, SequenceBuilder,
+        Translate,
+    },
+};
+
+/// Merged scale factors this close to 1 are snapped to exactly 1,
+/// so that a [Scale] followed by its reciprocal cancels out.
+const SNAP_EPSILON: f64 = 4.0 * f64::EPSILON;
+
+/// Adjacent linear transformations whose composition is this close to an identity
+/// are considered to be each other's inverse.
+const CANCEL_TOLERANCE: f64 = 1e-12;
+
+/// Rewrite any transformation tree into an equivalent one which is cheaper to evaluate.
+///
+/// Rewrites are applied recursively until none apply:
+///
+/// - identities are dropped from [Sequence]s, [ByDimension]s and [Bijection]s
+/// - nested [Sequence]s are flattened
+/// - adjacent transformation/inverse pairs are cancelled,
+///   e.g. a [Translate] followed by its negation
+/// - consecutive [MapAxis] are merged,
+///   as are consecutive [Scale]s and consecutive [Translate]s
+/// - [MapAxis] is pushed later through [Scale] and [Translate],
+///   so that it can merge with any subsequent [MapAxis]
+/// - [ByDimension]s whose children are all linear are collapsed into a single transformation
+///   (see [Affine::from_transform])
+///
+/// Transformations which cannot be simplified are returned as-is.
+pub fn simplify(t: Arc<dyn Transformation>) -> Arc<dyn Transformation> {
+    if t.is::<Identity>() {
+        return t;
+    }
+    if t.is_identity() {
+        return Arc::new(Identity::new(t.input_ndim()));
+    }
+    if let Some(seq) = t.downcast_ref::<Sequence>() {
+        return simplify_sequence(seq);
+    }
+    if let Some(bd) = t.downcast_ref::<ByDimension>() {
+        return simplify_by_dimension(bd).unwrap_or(t);
+    }
+    if let Some(bij) = t.downcast_ref::<Bijection>() {
+        let forward = simplify(bij.forward().clone());
+        let reverse = simplify(bij.reverse().clone());
+        return match Bijection::try_new_arc(forward, reverse) {
+            Ok(b) => Arc::new(b),
+            Err(_) => t,
+        };
+    }
+    t
+}
+
+fn simplify_sequence(seq: &Sequence) -> Arc<dyn Transformation> {
+    let mut flat = Vec::with_capacity(seq.transforms().len());
+    for inner in seq.transforms() {
+        let inner = simplify(inner.clone());
+        if let Some(inner_seq) = inner.downcast_ref::<Sequence>() {
+            // already simplified, so contains no sequences or identities
+            flat.extend(inner_seq.transforms().iter().cloned());
+        } else if !inner.is_identity() {
+            flat.push(inner);
+        }
+    }
+
+    while rewrite_first_pair(&mut flat) {}
+
+    if flat.is_empty() {
+        return Arc::new(Identity::new(seq.input_ndim()));
+    }
+    let mut builder = SequenceBuilder::with_capacity(flat.len());
+    for t in flat {
+        builder
+            .add_arced(t)
+            .expect("rewrites preserve dimensionality");
+    }
+    builder.build_any().expect("sequence is not empty")
+}
+
+/// Rewrite the first adjacent pair of transforms for which a rule applies.
+/// Returns whether a rewrite happened.
+fn rewrite_first_pair(transforms: &mut Vec<Arc<dyn Transformation>>) -> bool {
+    for idx in 1..transforms.len() {
+        if let Some(replacement) = rewrite_pair(&transforms[idx - 1], &transforms[idx]) {
+            transforms.splice(
+                (idx - 1)..=idx,
+                replacement.into_iter().filter(|t| !t.is_identity()),
+            );
+            return true;
+        }
+    }
+    false
+}
+
+fn rewrite_pair(
+    first: &Arc<dyn Transformation>,
+    second: &Arc<dyn Transformation>,
+) -> Option<ShortVec<Arc<dyn Transformation>>> {
+    if let Some(m1) = first.downcast_ref::<MapAxis>() {
+        if let Some(m2) = second.downcast_ref::<MapAxis>() {
+            let map: ShortVec<usize> = m2.map().iter().map(|idx| m1.map()[*idx]).collect();
+            return Some(smallvec![Arc::new(MapAxis::try_new(&map).ok()?) as _]);
+        }
+        if let Some(s) = second.downcast_ref::<Scale>() {
+            let factors = permute_for_map_axis(m1, s.factors());
+            return Some(smallvec![
+                Arc::new(Scale::try_new(&factors).ok()?) as _,
+                first.clone()
+            ]);
+        }
+        if let Some(t) = second.downcast_ref::<Translate>() {
+            let translation = permute_for_map_axis(m1, t.translation());
+            return Some(smallvec![
+                Arc::new(Translate::try_new(&translation).ok()?) as _,
+                first.clone()
+            ]);
+        }
+    }
+
+    if let (Some(t1), Some(t2)) = (
+        first.downcast_ref::<Translate>(),
+        second.downcast_ref::<Translate>(),
+    ) {
+        let translation: ShortVec<f64> = t1
+            .translation()
+            .iter()
+            .zip(t2.translation())
+            .map(|(a, b)| a + b)
+            .collect();
+        return Some(smallvec![
+            Arc::new(Translate::try_new(&translation).ok()?) as _
+        ]);
+    }
+
+    if let (Some(s1), Some(s2)) = (
+        first.downcast_ref::<Scale>(),
+        second.downcast_ref::<Scale>(),
+    ) {
+        let factors: ShortVec<f64> = s1
+            .factors()
+            .iter()
+            .zip(s2.factors())
+            .map(|(a, b)| {
+                let f = a * b;
+                if (f - 1.0).abs() <= SNAP_EPSILON {
+                    1.0
+                } else {
+                    f
+                }
+            })
+            .collect();
+        return Some(smallvec![Arc::new(Scale::try_new(&factors).ok()?) as _]);
+    }
+
+    if let (Some(b1), Some(b2)) = (
+        first.downcast_ref::<Bijection>(),
+        second.downcast_ref::<Bijection>(),
+    ) && Arc::ptr_eq(b1.forward(), b2.reverse())
+        && Arc::ptr_eq(b1.reverse(), b2.forward())
+    {
+        return Some(smallvec![]);
+    }
+
+    // Fall back to cancelling any pair of linear transforms which are each other's inverse.
+    if first.input_ndim() == second.output_ndim() {
+        let a1 = Affine::from_transform(first.as_ref())?;
+        let a2 = Affine::from_transform(second.as_ref())?;
+        if is_near_identity(&a1.then(&a2).ok()?, CANCEL_TOLERANCE) {
+            return Some(smallvec![]);
+        }
+    }
+
+    None
+}
+
+/// Re-order per-dimension values which would be applied after a [MapAxis]
+/// so that they can be applied before it instead.
+fn permute_for_map_axis(map_axis: &MapAxis, values: &[f64]) -> ShortVec<f64> {
+    let mut out: ShortVec<f64> = smallvec![f64::NAN; values.len()];
+    for (v, in_idx) in values.iter().zip(map_axis.map()) {
+        out[*in_idx] = *v;
+    }
+    out
+}
+
+fn simplify_by_dimension(bd: &ByDimension) -> Option<Arc<dyn Transformation>> {
+    let mut builder = ByDimension::builder(bd.input_ndim(), bd.output_ndim());
+    for (inner, in_dims, out_dims) in bd.sub_transforms() {
+        builder
+            .add_any(simplify(inner.clone()), in_dims, out_dims)
+            .ok()?;
+    }
+    let rebuilt = builder.build_any().ok()?;
+    match Affine::from_transform(rebuilt.as_ref()) {
+        Some(aff) => Some(simplest_linear(aff)),
+        None => Some(rebuilt),
+    }
+}
+
+fn is_near_identity(aff: &Affine, tolerance: f64) -> bool {
+    let m = aff.matrix();
+    if m.nrows() != m.ncols() {
+        return false;
+    }
+    if aff.translation().iter().any(|t| t.abs() > tolerance) {
+        return false;
+    }
+    (0..m.nrows()).all(|r| {
+        (0..m.ncols()).all(|c| {
+            let expected = if r == c { 1.0 } else { 0.0 };
+            (m[(r, c)] - expected).abs() <= tolerance
+        })
+    })
+}
+
+/// Represent an affine as the most specific type of transformation available.
+fn simplest_linear(aff: Affine) -> Arc<dyn Transformation> {
+    let m = aff.matrix();
+    let ndim = m.nrows();
+    if ndim != m.ncols() {
+        return Arc::new(aff);
+    }
+    if m.is_identity() {
+        return match Translate::try_new(aff.translation()) {
+            Ok(t) if t.is_identity() => Arc::new(Identity::new(ndim)),
+            Ok(t) => Arc::new(t),
+            Err(_) => Arc::new(aff),
+        };
+    }
+    if aff.translation().iter().any(|t| *t != 0.0) {
+        return Arc::new(aff);
+    }
+
+    let mut diagonal = true;
+    let mut map: ShortVec<usize> = smallvec![usize::MAX; ndim];
+    for (r, in_idx) in map.iter_mut().enumerate() {
+        for c in 0..ndim {
+            let val = m[(r, c)];
+            if r != c && val != 0.0 {
+                diagonal = false;
+            }
+            if val == 1.0 && *in_idx == usize::MAX {
+                *in_idx = c;
+            } else if val != 0.0 {
+                *in_idx = usize::MAX - 1;
+            }
+        }
+    }
+    if diagonal {
+        let factors: ShortVec<f64> = (0..ndim).map(|idx| m[(idx, idx)]).collect();
+        if let Ok(s) = Scale::try_new(&factors) {
+            return Arc::new(s);
+        }
+    } else if let Ok(map_axis) = MapAxis::try_new(&map) {
+        return Arc::new(map_axis);
+    }
+    Arc::new(aff)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::simplify;
+    use crate::{
+        Matrix, Transformation,
+        tests::check_equivalent,
+        transforms::{
+            Affine, ByDimension, Identity, MapAxis, Rotation, Scale, Sequence, Translate,
+        },
+    };
+
+    fn seq(transforms: Vec<Arc<dyn Transformation>>) -> Arc<dyn Transformation> {
+        let mut builder = Sequence::builder();
+        for t in transforms {
+            builder.add_arced(t).unwrap();
+        }
+        Arc::new(builder.build().unwrap())
+    }
+
+    fn scale(s: &[f64]) -> Arc<dyn Transformation> {
+        Arc::new(Scale::try_new(s).unwrap())
+    }
+
+    fn translate(t: &[f64]) -> Arc<dyn Transformation> {
+        Arc::new(Translate::try_new(t).unwrap())
+    }
+
+    fn map_axis(m: &[usize]) -> Arc<dyn Transformation> {
+        Arc::new(MapAxis::try_new(m).unwrap())
+    }
+
+    #[test]
+    fn test_flatten() {
+        let t = seq(vec![
+            seq(vec![scale(&[1.0, 2.0, 3.0]), translate(&[1.0, 2.0, 3.0])]),
+            seq(vec![scale(&[4.0, 5.0, 6.0]), translate(&[4.0, 5.0, 6.0])]),
+        ]);
+        let simple = simplify(t.clone());
+        let simple_seq = simple.downcast_ref::<Sequence>().unwrap();
+        assert_eq!(simple_seq.transforms().len(), 4);
+        assert!(!simple_seq.transforms().iter().any(|t| t.is::<Sequence>()));
+        check_equivalent(t.as_ref(), simple.as_ref());
+    }
+
+    #[test]
+    fn test_cancel_translate() {
+        let t = seq(vec![
+            scale(&[1.0, 2.0, 3.0]),
+            translate(&[1.0, -2.0, 3.5]),
+            translate(&[-1.0, 2.0, -3.5]),
+        ]);
+        let simple = simplify(t.clone());
+        assert!(simple.is::<Scale>());
+        check_equivalent(t.as_ref(), simple.as_ref());
+    }
+
+    #[test]
+    fn test_cancel_scale() {
+        let s = Scale::try_new(&[3.0, 7.0, 0.1]).unwrap();
+        let t = seq(vec![Arc::new(s.clone()), s.invert().unwrap()]);
+        assert!(simplify(t).is::<Identity>());
+    }
+
+    #[test]
+    fn test_cancel_rotation() {
+        #[rustfmt::skip]
+        let data = vec![
+            0.0, -1.0, 0.0,
+            1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0,
+        ];
+        let r = Rotation::try_new(Matrix::try_new(data, 3).unwrap()).unwrap();
+        let t = seq(vec![Arc::new(r.clone()), r.invert().unwrap()]);
+        assert!(simplify(t).is::<Identity>());
+    }
+
+    #[test]
+    fn test_merge_map_axis() {
+        let t = seq(vec![map_axis(&[2, 0, 1]), map_axis(&[2, 0, 1])]);
+        let simple = simplify(t.clone());
+        assert!(simple.is::<MapAxis>());
+        check_equivalent(t.as_ref(), simple.as_ref());
+
+        let t2 = seq(vec![map_axis(&[2, 0, 1]), map_axis(&[1, 2, 0])]);
+        assert!(simplify(t2).is::<Identity>());
+    }
+
+    #[test]
+    fn test_push_map_axis() {
+        let t = seq(vec![
+            map_axis(&[2, 0, 1]),
+            scale(&[1.0, 2.0, 3.0]),
+            translate(&[4.0, 5.0, 6.0]),
+            map_axis(&[1, 0, 2]),
+        ]);
+        let simple = simplify(t.clone());
+        let simple_seq = simple.downcast_ref::<Sequence>().unwrap();
+        assert_eq!(simple_seq.transforms().len(), 3);
+        assert!(simple_seq.transforms()[2].is::<MapAxis>());
+        check_equivalent(t.as_ref(), simple.as_ref());
+    }
+
+    #[test]
+    fn test_collapse_by_dimension() {
+        let mut builder = ByDimension::builder(3, 3);
+        builder
+            .add_transform(Translate::try_new(&[-1.0, 2.0]).unwrap(), &[0, 2], &[1, 0])
+            .unwrap()
+            .add_transform(Scale::try_new(&[100.0]).unwrap(), &[1], &[2])
+            .unwrap();
+        let t: Arc<dyn Transformation> = Arc::new(builder.build().unwrap());
+        let simple = simplify(t.clone());
+        assert!(simple.is::<Affine>());
+        check_equivalent(t.as_ref(), simple.as_ref());
+    }
+
+    #[test]
+    fn test_collapse_by_dimension_scale() {
+        let mut builder = ByDimension::builder(3, 3);
+        builder
+            .add_transform(Scale::try_new(&[2.0, 3.0]).unwrap(), &[0, 1], &[0, 1])
+            .unwrap()
+            .add_transform(Scale::try_new(&[4.0]).unwrap(), &[2], &[2])
+            .unwrap();
+        let t: Arc<dyn Transformation> = Arc::new(builder.build().unwrap());
+        let simple = simplify(t.clone());
+        assert_eq!(
+            simple.downcast_ref::<Scale>().unwrap().factors(),
+            &[2.0, 3.0, 4.0]
+        );
+        check_equivalent(t.as_ref(), simple.as_ref());
+    }
+}
@@ -20,6 +20,11 @@ impl Translate {
         }
         Ok(Self(translate.to_smallvec()))
     }
+
+    /// The value added to each dimension.
+    pub fn translation(&self) -> &[f64] {
+        &self.0
+    }
 }
 
 impl Transformation for Translate {
@@ -49,6 +54,18 @@ impl Transformation for Translate {
         self.0.len()
     }
 
+    fn jacobian_into(&self, _pt: &[f64], buf: &mut [f64]) {
+        let ndim = self.0.len();
+        buf.fill(0.0);
+        for idx in 0..ndim {
+            buf[idx * ndim + idx] = 1.0;
+        }
+    }
+
+    fn estimated_cost(&self) -> f64 {
+        self.0.len() as f64
+    }
+
     fn is_identity(&self) -> bool {
         self.0.iter().all(|t| *t == 0.0)
     }